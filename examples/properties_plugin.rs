@@ -0,0 +1,109 @@
+//! Reference plugin for the `exec` project type.
+//!
+//! Handles `.properties` like files with one `key=value` pair per line, e.g. a build descriptor:
+//!
+//! ```text
+//! # comments starting with # or ! and blank lines are kept
+//! name=my-service
+//! version=1.2.3
+//! ```
+//!
+//! Build it with `cargo build --example properties_plugin` and point a files entry at it:
+//!
+//! ```toml
+//! [[files]]
+//! path = "build.properties"
+//! key = "version"
+//! type = "exec"
+//! command = "../target/debug/examples/properties_plugin"
+//! ```
+
+use std::{fs, io, process::ExitCode};
+
+use serde_json::{json, Value};
+
+/// Lines starting with `#` or `!` are comments
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with(['#', '!'])
+}
+
+/// The value of `key` in `content`
+fn read_version(content: &str, key: &str) -> Result<String, String> {
+    content
+        .lines()
+        .filter(|line| !is_comment(line))
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().to_string())
+        .ok_or_else(|| format!("the key {} doesn't exist", key))
+}
+
+/// `content` with the value of `key` replaced by `version`, everything else stays as it is
+fn update_project(content: &str, key: &str, version: &str) -> Result<String, String> {
+    let mut found = false;
+    let mut updated: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match line.split_once('=') {
+            Some((name, _)) if name.trim() == key && !is_comment(line) => {
+                found = true;
+                updated.push(format!("{}={}", name, version));
+            }
+            _ => updated.push(line.to_string()),
+        }
+    }
+    if !found {
+        return Err(format!("the key {} doesn't exist", key));
+    }
+    let mut updated = updated.join("\n");
+    if content.ends_with('\n') {
+        updated.push('\n');
+    }
+    Ok(updated)
+}
+
+fn handle(request: &Value) -> Result<Value, String> {
+    if request["protocol"] != 1 {
+        return Err(format!("unsupported protocol {}", request["protocol"]));
+    }
+    let field = |name: &str| {
+        request[name]
+            .as_str()
+            .ok_or_else(|| format!("the request has no {}", name))
+    };
+    let path = field("path")?;
+    let key = field("key")?;
+    let content =
+        fs::read_to_string(path).map_err(|err| format!("failed to read {}: {}", path, err))?;
+    match field("method")? {
+        "read_version" => Ok(json!({ "version": read_version(&content, key)? })),
+        "update_project" => {
+            let mut content = update_project(&content, key, field("version")?)?;
+            // the values of the files entry, rendered already
+            for value in request["values"].as_array().into_iter().flatten() {
+                let (Some(key), Some(value)) = (value["key"].as_str(), value["value"].as_str())
+                else {
+                    return Err(format!("invalid value {}", value));
+                };
+                content = update_project(&content, key, value)?;
+            }
+            Ok(json!({ "content": content }))
+        }
+        method => Err(format!("unknown method {}", method)),
+    }
+}
+
+fn main() -> ExitCode {
+    let response = serde_json::from_reader(io::stdin())
+        .map_err(|err| format!("invalid request: {}", err))
+        .and_then(|request| handle(&request));
+    match response {
+        Ok(response) => {
+            println!("{}", response);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            println!("{}", json!({ "error": error }));
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use console::style;
+use glob::Pattern;
+use tracing::{info, instrument, warn};
+
+use crate::{
+    config::{Branch, Channel, MaintenanceRange},
+    git::BumpLevel,
+    scheme::{Clock, Version},
+    semver::SemanticVersion,
+};
+
+/// The first configured branch whose pattern matches `name`
+pub fn find_branch<'a>(branches: &'a [Branch], name: &str) -> eyre::Result<Option<&'a Branch>> {
+    for branch in branches {
+        let pattern = Pattern::new(&branch.name)
+            .map_err(|err| eyre::eyre!("Invalid branch pattern {}: {}", branch.name, err))?;
+        if pattern.matches(name) {
+            return Ok(Some(branch));
+        }
+    }
+    Ok(None)
+}
+
+/// The versions that were released already, read from the tags with the `tagprefix`
+pub fn released_versions(tags: &[String], tagprefix: &str) -> Vec<SemanticVersion> {
+    released_versions_with(tags, tagprefix, |version| version.parse())
+}
+
+/// Like [released_versions] for another version scheme, tags that `parse` refuses are skipped
+pub fn released_versions_with<V>(
+    tags: &[String],
+    tagprefix: &str,
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> Vec<V> {
+    tags.iter()
+        .filter_map(|tag| tag.strip_prefix(tagprefix))
+        .filter_map(|version| parse(version).ok())
+        .collect()
+}
+
+/// Picks the channel to release in from the checked out branch.
+/// Without configured branches everything is stable, otherwise a branch that doesn't match any of them
+/// (or a detached HEAD) only releases stable versions if `allow_unmatched` says so.
+#[instrument(level = "trace", skip(branches))]
+pub fn channel_for(
+    branches: &[Branch],
+    current_branch: Option<&str>,
+    allow_unmatched: bool,
+) -> eyre::Result<Channel> {
+    if branches.is_empty() {
+        return Ok(Channel::Stable);
+    }
+    let branch = match current_branch {
+        Some(current_branch) => find_branch(branches, current_branch)?,
+        None => None,
+    };
+    if let Some(branch) = branch {
+        info!(
+            "Branch {} releases on the {:?} channel",
+            style(&branch.name).bold(),
+            branch.channel
+        );
+        return Ok(branch.channel.clone());
+    }
+    let source = match current_branch {
+        Some(current_branch) => format!("The branch {}", current_branch),
+        None => "A detached HEAD".to_string(),
+    };
+    if !allow_unmatched {
+        return Err(eyre::eyre!(
+            "{} doesn't match any of the configured branches, so it has no release channel",
+            source
+        ))
+        .suggestion(
+            "Switch to one of the configured branches, add a [[branches]] entry for it, or pass --allow-unmatched-stable to release a stable version anyway",
+        );
+    }
+    warn!(
+        "{} doesn't match any configured branch, releasing on the stable channel",
+        source
+    );
+    Ok(Channel::Stable)
+}
+
+/// Calculates the version following `current` in the given channel.
+/// `released` are all versions that have a tag already, a maintenance release must not reach any of them.
+/// `initial_development` is passed on to [Version::next].
+#[instrument(level = "trace", skip(released))]
+pub fn next_version<V: Version>(
+    channel: &Channel,
+    current: &V,
+    bumplevel: BumpLevel,
+    released: &[V],
+    initial_development: bool,
+    clock: &dyn Clock,
+) -> eyre::Result<V> {
+    let next = current.next(channel, bumplevel, initial_development, clock)?;
+    if let Channel::Maintenance(range) = channel {
+        check_maintenance(range, current, &next, released)?;
+    }
+    Ok(next)
+}
+
+/// Makes sure `next` stays in the maintenance `range` and doesn't reach a version that was released after `current`
+fn check_maintenance<V: Version>(
+    range: &MaintenanceRange,
+    current: &V,
+    next: &V,
+    released: &[V],
+) -> eyre::Result<()> {
+    if !next.in_range(range) {
+        return Err(eyre::eyre!(
+            "{} would leave the maintenance range {}",
+            next,
+            range
+        ))
+        .suggestion(
+            "Release the change from a branch that allows it, or revert it on this branch",
+        );
+    }
+    if let Some(existing) = released
+        .iter()
+        .filter(|version| *version > current && *version <= next)
+        .min()
+    {
+        return Err(eyre::eyre!(
+            "{} would leave the maintenance line of {}, {} was released already",
+            next,
+            current,
+            existing
+        ))
+        .suggestion(
+            "Only fixes can be released from this branch, release the change from a newer branch",
+        );
+    }
+    Ok(())
+}
+
+/// Overrides the version the commits would lead to.
+/// The version to set is kept as it was given until [VersionOverride::parse] reads it with the scheme of the package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionOverride<V = String> {
+    /// Bump at least by this level, a higher level from the commits still wins.
+    /// The level isn't lowered during initial development, that takes [VersionOverride::PromoteToStable] for a major bump.
+    Bump(BumpLevel),
+    /// Release exactly this version
+    Set(V),
+    /// Leave the initial development and release 1.0.0
+    PromoteToStable,
+}
+
+impl VersionOverride {
+    /// Reads the version of [VersionOverride::Set] with `parse`, the parser of the package's scheme
+    pub fn parse<V>(
+        &self,
+        parse: impl Fn(&str) -> eyre::Result<V>,
+    ) -> eyre::Result<VersionOverride<V>> {
+        Ok(match self {
+            VersionOverride::Bump(bumplevel) => VersionOverride::Bump(*bumplevel),
+            VersionOverride::Set(version) => VersionOverride::Set(
+                parse(version)
+                    .wrap_err_with(|| format!("Failed to read the version {} to set", version))?,
+            ),
+            VersionOverride::PromoteToStable => VersionOverride::PromoteToStable,
+        })
+    }
+}
+
+/// The version of the release, calculated like [next_version] unless there is an override.
+/// A version passed with [VersionOverride::Set] has to fit the maintenance range of the channel as well.
+/// With `initial_development` the bumplevel of the commits is lowered during the initial development, see [Version::next].
+/// The result has to be greater than `current`, the version of the last tag.
+#[instrument(level = "trace", skip(released))]
+pub fn release_version<V: Version>(
+    channel: &Channel,
+    current: &V,
+    bumplevel: BumpLevel,
+    released: &[V],
+    version_override: Option<&VersionOverride<V>>,
+    initial_development: bool,
+    clock: &dyn Clock,
+) -> eyre::Result<V> {
+    V::check_channel(channel)?;
+    let next = match version_override {
+        None => next_version(
+            channel,
+            current,
+            bumplevel,
+            released,
+            initial_development,
+            clock,
+        )?,
+        Some(VersionOverride::Bump(forced)) => {
+            if initial_development
+                && *forced == BumpLevel::Major
+                && current.is_initial_development()
+            {
+                return Err(eyre::eyre!(
+                    "A major bump would end the initial development of {}",
+                    current
+                ))
+                .suggestion("Pass --promote-to-stable instead to release 1.0.0");
+            }
+            info!("Bumping at least by {:?}", style(forced).bold());
+            if *forced >= bumplevel {
+                // an explicit level is taken as it is
+                next_version(channel, current, *forced, released, false, clock)?
+            } else {
+                next_version(
+                    channel,
+                    current,
+                    bumplevel,
+                    released,
+                    initial_development,
+                    clock,
+                )?
+            }
+        }
+        Some(VersionOverride::Set(version)) => {
+            info!("Releasing the given version {}", style(version).bold());
+            if let Channel::Maintenance(range) = channel {
+                check_maintenance(range, current, version, released)?;
+            }
+            version.clone()
+        }
+        Some(VersionOverride::PromoteToStable) => {
+            if !current.is_initial_development() {
+                return Err(eyre::eyre!(
+                    "{} is a stable version already, there is nothing to promote",
+                    current
+                ))
+                .suggestion("Drop --promote-to-stable");
+            }
+            info!("Promoting {} to a stable version", style(current).bold());
+            next_version(channel, current, BumpLevel::Major, released, false, clock)?
+        }
+    };
+    if next <= *current {
+        return Err(eyre::eyre!(
+            "The new version {} has to be greater than {}, the version of the last tag",
+            next,
+            current
+        ))
+        .suggestion("Pass a higher version to --set-version");
+    }
+    if released.contains(&next) {
+        return Err(eyre::eyre!("{} was released already", next))
+            .suggestion("Check the tags, the version files might be behind them");
+    }
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use toml_edit::{de::from_document, Document};
+
+    use crate::{
+        config::Config,
+        scheme::{CalendarVersion, Date, FixedClock},
+    };
+
+    use super::*;
+
+    /// Semantic versions don't depend on the date
+    const CLOCK: FixedClock = FixedClock(Date {
+        year: 2026,
+        month: 10,
+        day: 18,
+    });
+
+    fn version(version: &str) -> SemanticVersion {
+        let mut semver = SemanticVersion::new();
+        semver.set_version(version).unwrap();
+        semver
+    }
+
+    fn parse(config: &str) -> Result<Vec<Branch>, String> {
+        let config = format!("subpath = \".\"\ntagprefix = \"v\"\nfiles = []\n{}", config);
+        let config: Result<Config, _> = from_document(config.parse::<Document>().unwrap());
+        config
+            .map(|config| config.branches)
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn parse_branches() {
+        let branches = parse(
+            r#"
+[[branches]]
+name = "main"
+
+[[branches]]
+name = "release/1.x"
+channel = "maintenance"
+range = "1.x"
+
+[[branches]]
+name = "next"
+channel = "prerelease"
+prerelease = "beta"
+"#,
+        )
+        .unwrap();
+        let channels: Vec<&Channel> = branches.iter().map(|branch| &branch.channel).collect();
+        assert_eq!(
+            channels,
+            vec![
+                &Channel::Stable,
+                &Channel::Maintenance(MaintenanceRange {
+                    major: 1,
+                    minor: None
+                }),
+                &Channel::Prerelease("beta".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse(r#"branches = ["main"]"#).unwrap()[0].channel,
+            Channel::Stable
+        );
+        let err = parse("[[branches]]\nname = \"x\"\nchannel = \"maintenance\"").unwrap_err();
+        assert!(err.contains("need a range"), "{}", err);
+        let err = parse("[[branches]]\nname = \"x\"\nchannel = \"maintenance\"\nrange = \"1\"")
+            .unwrap_err();
+        assert!(err.contains("not a valid maintenance range"), "{}", err);
+    }
+
+    #[test]
+    fn channel_from_branch() {
+        let branches = parse(
+            "branches = [\"main\", { name = \"release/*\", channel = \"maintenance\", range = \"1.2.x\" }]",
+        )
+        .unwrap();
+        let channel = channel_for(&branches, Some("release/1.2"), false).unwrap();
+        assert!(matches!(channel, Channel::Maintenance(_)));
+        assert_eq!(
+            channel_for(&branches, Some("main"), false).unwrap(),
+            Channel::Stable
+        );
+        assert_eq!(
+            channel_for(&[], Some("feature"), false).unwrap(),
+            Channel::Stable
+        );
+        assert!(channel_for(&branches, Some("feature"), false).is_err());
+        assert!(channel_for(&branches, None, false).is_err());
+        assert_eq!(
+            channel_for(&branches, Some("feature"), true).unwrap(),
+            Channel::Stable
+        );
+    }
+
+    #[test]
+    fn maintenance_range() {
+        let released = vec![version("1.2.3"), version("1.3.0"), version("2.0.0")];
+        let line = Channel::Maintenance("1.2.x".parse().unwrap());
+        let next = next_version(
+            &line,
+            &version("1.2.3"),
+            BumpLevel::Patch,
+            &released,
+            false,
+            &CLOCK,
+        )
+        .unwrap();
+        assert_eq!(next.to_string(), "1.2.4");
+        assert!(next_version(
+            &line,
+            &version("1.2.3"),
+            BumpLevel::Minor,
+            &released,
+            false,
+            &CLOCK
+        )
+        .is_err());
+
+        let major = Channel::Maintenance("1.x".parse().unwrap());
+        let err = next_version(
+            &major,
+            &version("1.2.3"),
+            BumpLevel::Minor,
+            &released,
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("1.3.0 was released already"),
+            "{}",
+            err
+        );
+        let next = next_version(
+            &major,
+            &version("1.3.0"),
+            BumpLevel::Minor,
+            &released,
+            false,
+            &CLOCK,
+        )
+        .unwrap();
+        assert_eq!(next.to_string(), "1.4.0");
+    }
+
+    #[test]
+    fn version_overrides() {
+        let current = version("1.2.3");
+        let forced = VersionOverride::Bump(BumpLevel::Patch);
+        let next = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::None,
+            &[],
+            Some(&forced),
+            false,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "1.2.4");
+        // the commits win if they lead to a higher level
+        let next = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Minor,
+            &[],
+            Some(&forced),
+            false,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "1.3.0");
+
+        let set = VersionOverride::Set(version("2.0.0"));
+        let next = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &[],
+            Some(&set),
+            false,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "2.0.0");
+        let set = VersionOverride::Set(version("1.2.3"));
+        let err = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &[],
+            Some(&set),
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("has to be greater than 1.2.3"),
+            "{}",
+            err
+        );
+        // a given version goes through the same checks as a calculated one
+        let released = vec![version("1.2.3"), version("1.3.0"), version("2.0.0")];
+        let set = VersionOverride::Set(version("2.0.0"));
+        let err = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            Some(&set),
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("2.0.0 was released already"),
+            "{}",
+            err
+        );
+        let line = Channel::Maintenance("1.2.x".parse().unwrap());
+        let set = VersionOverride::Set(version("1.4.0"));
+        let err = release_version(
+            &line,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            Some(&set),
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("leave the maintenance range 1.2.x"),
+            "{}",
+            err
+        );
+        let major = Channel::Maintenance("1.x".parse().unwrap());
+        let set = VersionOverride::Set(version("1.3.1"));
+        let err = release_version(
+            &major,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            Some(&set),
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("1.3.0 was released already"),
+            "{}",
+            err
+        );
+        let set = VersionOverride::Set(version("1.2.5"));
+        let next = release_version(
+            &line,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            Some(&set),
+            false,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "1.2.5");
+
+        let initial = version("0.4.2");
+        let next = release_version(
+            &Channel::Stable,
+            &initial,
+            BumpLevel::Major,
+            &[],
+            None,
+            true,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "0.5.0");
+        let next = release_version(
+            &Channel::Stable,
+            &initial,
+            BumpLevel::Minor,
+            &[],
+            None,
+            true,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "0.4.3");
+        let promote = VersionOverride::PromoteToStable;
+        let next = release_version(
+            &Channel::Stable,
+            &initial,
+            BumpLevel::Patch,
+            &[],
+            Some(&promote),
+            true,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "1.0.0");
+        assert!(release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &[],
+            Some(&promote),
+            true,
+            &CLOCK
+        )
+        .is_err());
+
+        // explicit bumps aren't lowered, a major one needs --promote-to-stable
+        let minor = VersionOverride::Bump(BumpLevel::Minor);
+        let next = release_version(
+            &Channel::Stable,
+            &initial,
+            BumpLevel::Minor,
+            &[],
+            Some(&minor),
+            true,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "0.5.0");
+        let major = VersionOverride::Bump(BumpLevel::Major);
+        let err = release_version(
+            &Channel::Stable,
+            &initial,
+            BumpLevel::Patch,
+            &[],
+            Some(&major),
+            true,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("end the initial development of 0.4.2"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn calendar_versions() {
+        let format = "YYYY.0M.MICRO".parse().unwrap();
+        let calver = |version: &str| CalendarVersion::parse(version, &format).unwrap();
+        let current = calver("2026.10.0");
+        let released = vec![calver("2026.09.3"), current.clone()];
+        let next = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            None,
+            true,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "2026.10.1");
+        let set = VersionOverride::Set("2026.10.5".to_string())
+            .parse(|raw| CalendarVersion::parse(raw, &format))
+            .unwrap();
+        let next = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::None,
+            &released,
+            Some(&set),
+            false,
+            &CLOCK,
+        );
+        assert_eq!(next.unwrap().to_string(), "2026.10.5");
+        assert!(VersionOverride::Set("2026.13.0".to_string())
+            .parse(|raw| CalendarVersion::parse(raw, &format))
+            .is_err());
+        let err = release_version(
+            &Channel::Stable,
+            &current,
+            BumpLevel::Patch,
+            &released,
+            Some(&VersionOverride::PromoteToStable),
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("stable version already"),
+            "{}",
+            err
+        );
+        let err = release_version(
+            &Channel::Prerelease("beta".to_string()),
+            &current,
+            BumpLevel::Patch,
+            &released,
+            None,
+            false,
+            &CLOCK,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("only be released on the stable channel"),
+            "{}",
+            err
+        );
+    }
+}
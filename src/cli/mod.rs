@@ -1,19 +1,174 @@
-use clap::{arg, Parser};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
 use color_eyre::eyre;
 use tracing_subscriber::filter::LevelFilter;
 
+use semantic_release::channel::VersionOverride;
+use semantic_release::config::source::Overrides;
+use semantic_release::consistency::SyncTarget;
+use semantic_release::git::backend::BackendKind;
+use semantic_release::git::BumpLevel;
+use semantic_release::init::InitOptions;
+use semantic_release::notes::NotesFormat;
+use semantic_release::preflight::Allow;
+use semantic_release::report::DiffFormat;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Validate a config file and the project files it points to without changing anything
+    Check {
+        /// Path to the config file, discovered from the current directory if omitted
+        config: Option<String>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// Print the JSON Schema of the config file, e.g. for editor completion
+    Schema,
+    /// Inspect the config
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Compute the next release without changing anything and print it as JSON, see apply
+    Plan {
+        /// Write the plan to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        release: ReleaseArgs,
+        #[command(flatten)]
+        overrides: Overrides,
+        /// Path to the config file, discovered from the current directory if omitted
+        config: Option<String>,
+    },
+    /// Release a plan written by plan --out, HEAD must not have moved since then
+    Apply {
+        /// The plan file
+        plan: PathBuf,
+        /// Path to the config file, discovered from the current directory if omitted
+        #[arg(long)]
+        config: Option<String>,
+        #[command(flatten)]
+        allow: Allow,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// Check commit messages against the commit template and the configured types and scopes
+    Lint {
+        /// File with the message, e.g. the one git passes to the commit-msg hook. Reads stdin if omitted or -
+        file: Option<PathBuf>,
+        /// Lint every commit of a revision range instead, e.g. v1.2.0..HEAD
+        #[arg(long, conflicts_with = "file")]
+        range: Option<String>,
+        /// Install semantic-release lint as the commit-msg hook of the repository
+        #[arg(long, conflicts_with_all = ["file", "range"])]
+        install_hook: bool,
+        /// Path to the config file, discovered from the current directory if omitted
+        #[arg(long)]
+        config: Option<String>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// Print release notes of the commits in a range, grouped like they count towards the version
+    Notes {
+        /// unreleased for the commits since the latest release, a release tag like v1.3.0 or a range like v1.2.0..v1.3.0
+        #[arg(default_value = "unreleased")]
+        range: String,
+        /// How the notes get printed
+        #[arg(long, value_enum, default_value_t = NotesFormat::Markdown)]
+        format: NotesFormat,
+        /// Path to the config file, discovered from the current directory if omitted
+        #[arg(long)]
+        config: Option<String>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+    /// Print the changelog of every release tag with the tagprefix
+    Changelog {
+        /// Write the changelog to the file instead of printing it, replacing what's in there
+        #[arg(long)]
+        rebuild: bool,
+        /// The changelog file relative to the subpath, defaults to the changelog of the config or CHANGELOG.md
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Path to the config file, discovered from the current directory if omitted
+        #[arg(long)]
+        config: Option<String>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+}
+
+/// What gets released instead of the version the commits lead to
+#[derive(Args, Debug, Clone)]
+pub struct ReleaseArgs {
+    /// Bump at least by this level, e.g. to release without any conventional commits
+    #[arg(long, value_enum, conflicts_with_all = ["set_version", "sync"])]
+    bump: Option<BumpLevel>,
+    /// Release exactly this version, it has to be greater than the version of the last tag
+    #[arg(long, value_name = "VERSION", conflicts_with = "sync")]
+    set_version: Option<String>,
+    /// Release 1.0.0 from a 0.y.z version, e.g. at the end of the initial development
+    #[arg(long, conflicts_with_all = ["bump", "set_version", "sync"])]
+    promote_to_stable: bool,
+    /// Align the versions of all configured files instead of releasing, without a bump or a tag
+    #[arg(long, value_enum)]
+    sync: Option<SyncTarget>,
+}
+
+impl ReleaseArgs {
+    fn version_override(&self) -> Option<VersionOverride> {
+        match (self.bump, &self.set_version) {
+            _ if self.promote_to_stable => Some(VersionOverride::PromoteToStable),
+            (_, Some(version)) => Some(VersionOverride::Set(version.clone())),
+            (Some(bumplevel), None) => Some(VersionOverride::Bump(bumplevel)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Print the config after discovery and overrides, together with where it came from
+    Show {
+        /// Path to the config file, discovered from the current directory if omitted
+        config: Option<String>,
+        #[command(flatten)]
+        overrides: Overrides,
+    },
+}
+
 #[derive(Parser, Debug)]
+#[command(about = None, args_conflicts_with_subcommands = true)]
 struct CliArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Log debug infos, may be passed more than once to increase log level
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
-    /// Don't actually change any files or do git commits/tags
+    /// Don't actually change any files or do git commits/tags, print what would happen instead
     #[arg(short, long, default_value_t = false)]
     dry: bool,
-    /// Create a config file at the given path instead of doing any semantic releasing
+    /// How the dry run prints the changes it would make
+    #[arg(long, value_enum, default_value_t = DiffFormat::Unified)]
+    diff_format: DiffFormat,
+    #[command(flatten)]
+    release: ReleaseArgs,
+    /// Create a config file at the given path (project.toml by default) instead of doing any semantic releasing
     #[arg(long, default_value_t = false)]
     init: bool,
-    config: String,
+    #[command(flatten)]
+    init_options: InitOptions,
+    /// How to talk to git, the cli backend needs git to be installed
+    #[arg(long, value_enum, default_value_t = BackendKind::Gitoxide, global = true)]
+    git_backend: BackendKind,
+    #[command(flatten)]
+    allow: Allow,
+    #[command(flatten)]
+    overrides: Overrides,
+    /// Path to the config file, discovered from the current directory if omitted
+    config: Option<String>,
 }
 impl CliArgs {
     pub fn log_level(&self) -> LevelFilter {
@@ -27,10 +182,19 @@ impl CliArgs {
 
 #[derive(Debug)]
 pub struct CliContext {
-    pub path: String,
+    pub command: Option<Command>,
+    /// The config file given on the command line
+    pub config: Option<String>,
     pub log_level: LevelFilter,
     pub dryrun: bool,
+    pub diff_format: DiffFormat,
+    pub sync: Option<SyncTarget>,
+    pub version_override: Option<VersionOverride>,
     pub init: bool,
+    pub init_options: InitOptions,
+    pub git_backend: BackendKind,
+    pub allow: Allow,
+    pub overrides: Overrides,
 }
 
 impl CliContext {
@@ -42,11 +206,52 @@ impl CliContext {
         let dryrun = cli.dry;
         let init = cli.init;
 
+        let (config, overrides) = match &cli.command {
+            Some(Command::Check { config, overrides })
+            | Some(Command::Lint {
+                config, overrides, ..
+            })
+            | Some(Command::Config {
+                action: ConfigCommand::Show { config, overrides },
+            })
+            | Some(Command::Plan {
+                config, overrides, ..
+            })
+            | Some(Command::Apply {
+                config, overrides, ..
+            })
+            | Some(Command::Notes {
+                config, overrides, ..
+            })
+            | Some(Command::Changelog {
+                config, overrides, ..
+            }) => (config.clone(), overrides.clone()),
+            Some(Command::Schema) | None => (cli.config, cli.overrides),
+        };
+        let release = match &cli.command {
+            Some(Command::Plan { release, .. }) => release,
+            _ => &cli.release,
+        };
+        let sync = release.sync;
+        let version_override = release.version_override();
+        let allow = match &cli.command {
+            Some(Command::Apply { allow, .. }) => *allow,
+            _ => cli.allow,
+        };
+
         Ok(CliContext {
-            path: cli.config,
+            command: cli.command,
+            config,
             log_level,
             dryrun,
+            diff_format: cli.diff_format,
+            sync,
+            version_override,
             init,
+            init_options: cli.init_options,
+            git_backend: cli.git_backend,
+            allow,
+            overrides,
         })
     }
 }
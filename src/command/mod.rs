@@ -0,0 +1,209 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+use tracing::debug;
+
+/// Everything needed to figure out why an external command didn't work out
+#[derive(Debug)]
+pub enum CommandError {
+    /// The command couldn't be started at all, most likely because the executable doesn't exist
+    Spawn {
+        command_line: String,
+        cwd: PathBuf,
+        source: std::io::Error,
+    },
+    /// The command ran but exited with a non-zero status
+    Failed {
+        command_line: String,
+        cwd: PathBuf,
+        /// `None` if the command was terminated by a signal
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Spawn {
+                command_line, cwd, ..
+            } => write!(f, "Failed to run `{}` in {}", command_line, cwd.display()),
+            CommandError::Failed {
+                command_line,
+                cwd,
+                exit_code,
+                stderr,
+            } => {
+                match exit_code {
+                    Some(code) => write!(
+                        f,
+                        "`{}` in {} exited with code {}",
+                        command_line,
+                        cwd.display(),
+                        code
+                    )?,
+                    None => write!(
+                        f,
+                        "`{}` in {} was terminated by a signal",
+                        command_line,
+                        cwd.display()
+                    )?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Spawn { source, .. } => Some(source),
+            CommandError::Failed { .. } => None,
+        }
+    }
+}
+
+fn command_line(executable: &str, args: &[String]) -> String {
+    let mut parts = vec![executable.to_string()];
+    parts.extend(args.iter().map(|arg| {
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            format!("{:?}", arg)
+        } else {
+            arg.to_string()
+        }
+    }));
+    parts.join(" ")
+}
+
+/// Runs the command and fails unless it exits successfully
+pub fn run_command(
+    executable: &str,
+    cwd: &Path,
+    args: Vec<String>,
+) -> Result<Output, CommandError> {
+    let command_line = command_line(executable, &args);
+    let mut command = Command::new(executable);
+    command.args(args).current_dir(cwd);
+    debug!("Executing {} in {:?}", command_line, cwd);
+    let output = command.output().map_err(|source| CommandError::Spawn {
+        command_line: command_line.clone(),
+        cwd: cwd.to_path_buf(),
+        source,
+    })?;
+    if !output.status.success() {
+        return Err(CommandError::Failed {
+            command_line,
+            cwd: cwd.to_path_buf(),
+            exit_code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output)
+}
+
+/// Runs `script` with the system shell and the additional environment variables `envs`.
+/// stdout goes to stderr to keep stdout free for reports, stderr is shown as it comes and ends up in the error as well.
+pub fn run_shell(script: &str, cwd: &Path, envs: &[(&str, String)]) -> Result<(), CommandError> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut command = Command::new(shell);
+    command
+        .args([flag, script])
+        .current_dir(cwd)
+        .envs(envs.iter().map(|(key, value)| (key, value)))
+        .stdout(Stdio::from(io::stderr()))
+        .stderr(Stdio::piped());
+    debug!("Executing {} in {:?}", script, cwd);
+    let spawn_error = |source| CommandError::Spawn {
+        command_line: script.to_string(),
+        cwd: cwd.to_path_buf(),
+        source,
+    };
+    let mut child = command.spawn().map_err(spawn_error)?;
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let tee = thread::spawn(move || {
+        let mut captured = String::new();
+        for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line);
+            eprintln!("{}", line);
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+        captured
+    });
+    let status = child.wait().map_err(spawn_error)?;
+    let stderr = tee.join().unwrap_or_default();
+    if !status.success() {
+        return Err(CommandError::Failed {
+            command_line: script.to_string(),
+            cwd: cwd.to_path_buf(),
+            exit_code: status.code(),
+            stderr: stderr.trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn failing_command_reports_context() {
+        let dir = TempDir::new().unwrap();
+        let args = vec!["tag".to_string(), "not a valid tag".to_string()];
+        let err = run_command("git", dir.path(), args).unwrap_err();
+        match &err {
+            CommandError::Failed {
+                command_line,
+                cwd,
+                exit_code,
+                stderr,
+            } => {
+                assert_eq!(command_line, "git tag \"not a valid tag\"");
+                assert_eq!(cwd, dir.path());
+                assert_eq!(*exit_code, Some(128));
+                assert!(stderr.contains("not a git repository"), "{}", stderr);
+            }
+            CommandError::Spawn { .. } => panic!("git should have been started"),
+        }
+        assert!(err.to_string().contains("exited with code 128"));
+    }
+
+    #[test]
+    fn shell_with_environment() {
+        let dir = TempDir::new().unwrap();
+        let envs = [("SR_TEST", "hello".to_string())];
+        run_shell("test \"$SR_TEST\" = hello", dir.path(), &envs).unwrap();
+        let err = run_shell("exit 3", dir.path(), &envs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("`exit 3` in {} exited with code 3", dir.path().display())
+        );
+        let err = run_shell("echo broken >&2; exit 1", dir.path(), &envs).unwrap_err();
+        assert!(
+            err.to_string().ends_with("exited with code 1: broken"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn missing_executable() {
+        let dir = TempDir::new().unwrap();
+        let err = run_command("definitely-not-an-executable", dir.path(), vec![]).unwrap_err();
+        assert!(matches!(err, CommandError::Spawn { .. }));
+    }
+}
@@ -0,0 +1,493 @@
+use std::{collections::BTreeMap, fmt, fs, ops::Range, path::Path};
+
+use color_eyre::eyre;
+use console::style;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_spanned::Spanned;
+use tracing::{debug, info, instrument};
+
+use crate::{
+    project::load_versionfile,
+    scheme::{CalendarFormat, CalendarVersion},
+    semver::SemanticVersion,
+};
+
+use super::{
+    source::{locate, ConfigSource, Overrides},
+    Config, ProjectFile, Scheme,
+};
+
+/// A single thing wrong with a config file
+#[derive(Debug)]
+pub struct Problem {
+    pub message: String,
+    /// Byte range in the config file the problem is about
+    pub span: Option<Range<usize>>,
+}
+
+impl Problem {
+    fn new(message: String, span: Option<Range<usize>>) -> Problem {
+        Problem { message, span }
+    }
+}
+
+/// Every problem found in a config file, together with the content needed to point at them
+pub struct CheckResult {
+    pub path: String,
+    pub content: String,
+    pub problems: Vec<Problem>,
+}
+
+type Table = BTreeMap<Spanned<String>, Spanned<Value>>;
+
+#[derive(Deserialize)]
+struct FileEntries {
+    files: Option<Vec<Spanned<Table>>>,
+}
+
+/// The 1-based line and column of a byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+impl fmt::Display for CheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for problem in &self.problems {
+            let Some(span) = &problem.span else {
+                writeln!(f, "{}: {}", self.path, problem.message)?;
+                continue;
+            };
+            let (line, column) = line_column(&self.content, span.start);
+            writeln!(f, "{}:{}:{}: {}", self.path, line, column, problem.message)?;
+            let source = self.content.lines().nth(line - 1).unwrap_or_default();
+            let width = self.content[span.clone()]
+                .lines()
+                .next()
+                .map_or(1, |first| first.chars().count().max(1));
+            writeln!(f, "  {}", source)?;
+            writeln!(f, "  {}{}", " ".repeat(column - 1), "^".repeat(width))?;
+        }
+        Ok(())
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nothing",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "a table",
+    }
+}
+
+/// Checks that `key` is a string in `table`, `table_span` is used if the key is missing
+fn check_string(
+    table: &Table,
+    key: &str,
+    required: bool,
+    context: &str,
+    table_span: Option<Range<usize>>,
+    problems: &mut Vec<Problem>,
+) -> Option<(String, Range<usize>)> {
+    let Some(value) = table.get(key) else {
+        if required {
+            problems.push(Problem::new(
+                format!("{}missing key `{}`", context, key),
+                table_span,
+            ));
+        }
+        return None;
+    };
+    match value.get_ref() {
+        Value::String(string) => Some((string.clone(), value.span())),
+        other => {
+            problems.push(Problem::new(
+                format!(
+                    "{}`{}` has to be a string, found {}",
+                    context,
+                    key,
+                    value_type(other)
+                ),
+                Some(value.span()),
+            ));
+            None
+        }
+    }
+}
+
+fn check_structure(
+    content: &str,
+    overrides: &Overrides,
+    problems: &mut Vec<Problem>,
+) -> Option<Vec<Table>> {
+    let top: Table = match toml_edit::de::from_str(content) {
+        Ok(top) => top,
+        Err(err) => {
+            problems.push(Problem::new(err.message().trim().to_string(), err.span()));
+            return None;
+        }
+    };
+    let start = Some(0..0);
+    for (key, required) in [
+        ("tagprefix", true),
+        ("subpath", true),
+        ("revert_type", false),
+    ] {
+        // an overridden setting never gets used
+        if !overrides.overrides(key) {
+            check_string(&top, key, required, "", start.clone(), problems);
+        }
+    }
+    if !top.contains_key("files") {
+        problems.push(Problem::new("missing key `files`".to_string(), start));
+        return None;
+    }
+
+    let entries: FileEntries = match toml_edit::de::from_str(content) {
+        Ok(entries) => entries,
+        Err(err) => {
+            let message = err.message().trim();
+            problems.push(Problem::new(
+                format!("`files` has to be an array of tables: {}", message),
+                err.span(),
+            ));
+            return None;
+        }
+    };
+    let mut files = Vec::new();
+    for (i, entry) in entries.files.unwrap_or_default().into_iter().enumerate() {
+        let span = Some(entry.span());
+        let entry = entry.into_inner();
+        let context = format!("files[{}]: ", i);
+        check_string(&entry, "path", true, &context, span.clone(), problems);
+        let key = check_string(&entry, "key", true, &context, span.clone(), problems);
+        if let Some((project_type, type_span)) =
+            check_string(&entry, "type", true, &context, span.clone(), problems)
+        {
+            if !["json", "toml", "line", "exec"].contains(&project_type.as_str()) {
+                problems.push(Problem::new(
+                    format!(
+                        "{}unknown type `{}`, expected `json`, `toml`, `line` or `exec`",
+                        context, project_type
+                    ),
+                    Some(type_span),
+                ));
+            }
+            if let Some((key, key_span)) = key.filter(|_| project_type == "line") {
+                if !key.contains("{version}") {
+                    problems.push(Problem::new(
+                        format!(
+                            "{}the line type needs `{{version}}` where the version is in the `key`",
+                            context
+                        ),
+                        Some(key_span),
+                    ));
+                }
+            }
+            check_string(&entry, "command", false, &context, None, problems);
+            if project_type == "exec" && !entry.contains_key("command") {
+                problems.push(Problem::new(
+                    format!("{}the exec type needs a `command`", context),
+                    span.clone(),
+                ));
+            }
+        }
+        files.push(entry);
+    }
+    Some(files)
+}
+
+fn check_file(
+    directory: &Path,
+    file: &ProjectFile,
+    entry: &Table,
+    index: usize,
+    format: Option<&CalendarFormat>,
+    problems: &mut Vec<Problem>,
+) {
+    let span_of = |key: &str| entry.get(key).map(|value| value.span());
+    let filepath = directory.join(&file.path);
+    if !filepath.is_file() {
+        problems.push(Problem::new(
+            format!("files[{}]: {} doesn't exist", index, file.path),
+            span_of("path"),
+        ));
+        return;
+    }
+    let version = load_versionfile(&filepath, file).and_then(|versionfile| {
+        let version = versionfile.read_version()?;
+        match format {
+            Some(format) => {
+                CalendarVersion::parse(&version, format)?;
+            }
+            None => SemanticVersion::new().set_version(&version)?,
+        }
+        Ok(version)
+    });
+    match version {
+        Ok(version) => debug!("{} has the version {}", file.path, version),
+        Err(err) => problems.push(Problem::new(
+            format!(
+                "files[{}]: `{}` in {} doesn't resolve to a valid version: {:#}",
+                index, file.key, file.path, err
+            ),
+            span_of("key"),
+        )),
+    }
+}
+
+/// Reads the content to check, embedded configs are turned into a config file of their own
+fn read_content(source: &ConfigSource) -> Result<String, String> {
+    if source.section.is_none() {
+        return fs::read_to_string(&source.path)
+            .map_err(|err| format!("failed to read the file: {}", err));
+    }
+    let value = source.read().map_err(|err| format!("{:#}", err))?;
+    toml_edit::ser::to_string_pretty(&value)
+        .map_err(|err| format!("failed to read the config section: {}", err))
+}
+
+/// Validates the config at `source` with the `overrides` applied, like a release would use it, without changing anything.
+/// Every problem is collected instead of stopping at the first one, paths are checked relative to the config file.
+/// Configs embedded in a manifest are reported without line and column.
+#[instrument(level = "trace")]
+pub fn check(source: &ConfigSource, overrides: &Overrides) -> CheckResult {
+    let mut result = CheckResult {
+        path: source.to_string(),
+        content: String::new(),
+        problems: Vec::new(),
+    };
+    result.content = match read_content(source) {
+        Ok(content) => content,
+        Err(message) => {
+            result.problems.push(Problem::new(message, None));
+            return result;
+        }
+    };
+    check_content(source, &result.content, overrides, &mut result.problems);
+    if source.section.is_some() {
+        // the spans point into the extracted section, not the manifest
+        for problem in &mut result.problems {
+            problem.span = None;
+        }
+    }
+    result
+}
+
+/// Deserializes the config like [super::source::load] does.
+/// Without overrides the file is deserialized directly to keep the location of errors.
+fn deserialize(content: &str, overrides: &Overrides) -> Result<Config, Problem> {
+    let error =
+        |err: toml_edit::de::Error| Problem::new(err.message().trim().to_string(), err.span());
+    if overrides.is_empty() {
+        return toml_edit::de::from_str(content).map_err(error);
+    }
+    let mut value: Value = toml_edit::de::from_str(content).map_err(error)?;
+    overrides.apply(&mut value);
+    serde_json::from_value(value).map_err(|err| Problem::new(err.to_string(), None))
+}
+
+fn check_content(
+    source: &ConfigSource,
+    content: &str,
+    overrides: &Overrides,
+    problems: &mut Vec<Problem>,
+) {
+    let Some(entries) = check_structure(content, overrides, problems) else {
+        return;
+    };
+    if !problems.is_empty() {
+        return;
+    }
+    // the remaining settings are only checked by the real deserialization
+    let config = match deserialize(content, overrides) {
+        Ok(config) => config,
+        Err(problem) => {
+            problems.push(problem);
+            return;
+        }
+    };
+
+    let directory = match source.path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => Path::new(".").to_path_buf(),
+    };
+    let directory = directory.join(&config.subpath);
+    if !directory.is_dir() {
+        problems.push(Problem::new(
+            format!("the subpath {} doesn't exist", directory.display()),
+            None,
+        ));
+        return;
+    }
+    let format = match &config.scheme {
+        Scheme::Semver => None,
+        Scheme::Calver { format } => match format.parse::<CalendarFormat>() {
+            Ok(format) => Some(format),
+            Err(err) => {
+                problems.push(Problem::new(format!("scheme: {}", err), None));
+                return;
+            }
+        },
+    };
+    for (index, (file, entry)) in config.files.iter().zip(&entries).enumerate() {
+        check_file(&directory, file, entry, index, format.as_ref(), problems);
+    }
+}
+
+/// Runs the check subcommand, printing every problem and failing if there are any
+pub fn check_config(path: Option<&str>, overrides: &Overrides) -> eyre::Result<()> {
+    let source = locate(path)?;
+    info!("Checking {}", style(&source).bold());
+    for overridden in overrides.describe() {
+        info!("Using the {}", overridden);
+    }
+    let result = check(&source, overrides);
+    if result.problems.is_empty() {
+        info!("No problems found");
+        return Ok(());
+    }
+    eprint!("{}", result);
+    Err(eyre::eyre!(
+        "Found {} problem(s) in {}",
+        result.problems.len(),
+        source
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn check_config(config: &str) -> (TempDir, CheckResult) {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nversion = \"1.2.3\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("package.json"), "{\"version\": \"x\"}").unwrap();
+        let path = dir.path().join("project.toml");
+        fs::write(&path, config).unwrap();
+        let result = check(
+            &ConfigSource::from_path(&path).unwrap(),
+            &Overrides::default(),
+        );
+        (dir, result)
+    }
+
+    #[test]
+    fn valid_config() {
+        let (_dir, result) = check_config(
+            "subpath = \".\"\ntagprefix = \"v\"\n[[files]]\npath = \"Cargo.toml\"\nkey = \"package.version\"\ntype = \"toml\"\n",
+        );
+        assert!(result.problems.is_empty(), "{}", result);
+    }
+
+    #[test]
+    fn reports_every_problem_with_a_span() {
+        let (_dir, result) = check_config(
+            "subpath = \".\"\ntagprefix = 1\n[[files]]\npath = \"Cargo.toml\"\nkey = \"package.version\"\ntype = \"yaml\"\n[[files]]\npath = \"package.json\"\n[[files]]\npath = \"Chart.yaml\"\nkey = \"version\"\ntype = \"line\"\n",
+        );
+        let report = result.to_string();
+        assert_eq!(result.problems.len(), 5, "{}", report);
+        assert!(report.contains(":2:13: `tagprefix` has to be a string, found a number"));
+        assert!(report.contains(":6:8: files[0]: unknown type `yaml`"));
+        assert!(report.contains("files[1]: missing key `key`"));
+        assert!(report.contains(
+            ":11:7: files[2]: the line type needs `{version}` where the version is in the `key`"
+        ));
+    }
+
+    #[test]
+    fn missing_files_and_versions() {
+        let (_dir, result) = check_config(
+            "subpath = \".\"\ntagprefix = \"v\"\n[[files]]\npath = \"missing.toml\"\nkey = \"version\"\ntype = \"toml\"\n[[files]]\npath = \"package.json\"\nkey = \"version\"\ntype = \"json\"\n",
+        );
+        let report = result.to_string();
+        assert_eq!(result.problems.len(), 2, "{}", report);
+        assert!(report.contains(":4:8: files[0]:"), "{}", report);
+        assert!(report.contains("missing.toml doesn't exist"));
+        assert!(report.contains(
+            ":9:7: files[1]: `version` in package.json doesn't resolve to a valid version"
+        ));
+    }
+
+    #[test]
+    fn embedded_config() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("package.json");
+        fs::write(
+            &path,
+            r#"{"version": "1.0.0", "semantic-release": {"subpath": ".", "tagprefix": "v", "files": [{"path": "package.json", "key": "version", "type": "json"}, {"path": "missing.json", "key": "version", "type": "json"}]}}"#,
+        )
+        .unwrap();
+        let result = check(
+            &ConfigSource::from_path(&path).unwrap(),
+            &Overrides::default(),
+        );
+        assert_eq!(result.problems.len(), 1, "{}", result);
+        assert!(result
+            .to_string()
+            .contains("package.json [semantic-release]: files[1]: missing.json doesn't exist"));
+    }
+
+    #[test]
+    fn overrides_are_applied() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("pkg")).unwrap();
+        fs::write(
+            dir.path().join("pkg/package.json"),
+            "{\"version\": \"1.0.0\"}",
+        )
+        .unwrap();
+        let path = dir.path().join("project.toml");
+        fs::write(
+            &path,
+            "subpath = \"missing\"\n[[files]]\npath = \"package.json\"\nkey = \"version\"\ntype = \"json\"\n",
+        )
+        .unwrap();
+        let source = ConfigSource::from_path(&path).unwrap();
+        let result = check(&source, &Overrides::default());
+        assert!(
+            result.to_string().contains("missing key `tagprefix`"),
+            "{}",
+            result
+        );
+
+        let overrides = Overrides {
+            tagprefix: Some("v".to_string()),
+            subpath: Some("pkg".to_string()),
+            ..Default::default()
+        };
+        let result = check(&source, &overrides);
+        assert!(result.problems.is_empty(), "{}", result);
+
+        let overrides = Overrides {
+            tagprefix: Some("v".to_string()),
+            subpath: Some("elsewhere".to_string()),
+            ..Default::default()
+        };
+        let result = check(&source, &overrides);
+        assert!(
+            result.to_string().contains("elsewhere doesn't exist"),
+            "{}",
+            result
+        );
+    }
+
+    #[test]
+    fn invalid_toml() {
+        let (_dir, result) = check_config("subpath = \".\"\ntagprefix = \n");
+        assert_eq!(result.problems.len(), 1);
+        assert!(result.to_string().contains(":2:"), "{}", result);
+    }
+}
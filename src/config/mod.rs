@@ -1,28 +1,85 @@
-use std::{fs, path::Path};
-
-use color_eyre::{
-    eyre::{self, WrapErr},
-    Help,
-};
-use console::style;
-use serde::Deserialize;
-use toml_edit::Document;
-use tracing::{debug, info};
-
-#[derive(Debug, Deserialize, Clone, Copy)]
+pub mod check;
+pub mod source;
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use color_eyre::eyre::{self, WrapErr};
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+
+use crate::scheme::CalendarFormat;
+
+/// How a project file is parsed
+#[derive(Debug, Deserialize, Serialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
 pub enum ProjectType {
+    /// A JSON file like a package.json
     #[serde(rename = "json")]
     Json,
+    /// A TOML file like a Cargo.toml
     #[serde(rename = "toml")]
     Toml,
+    /// Any text file, the `key` is the line with the version like `appVersion: "{version}"`, e.g. for YAML files
+    #[serde(rename = "line")]
+    Line,
+    /// Any file, read and updated by the executable in `command`
+    #[serde(rename = "exec")]
+    Exec,
 }
 
-#[derive(Deserialize, Debug)]
+/// How the versions of the package are numbered
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Scheme {
+    /// Semantic versions like 1.2.3, the commits decide which part gets bumped
+    #[default]
+    Semver,
+    /// Calendar versions like 2026.10.0, the day of the release decides the version
+    Calver {
+        /// The parts of the version separated by dots, e.g. `YYYY.0M.MICRO` or `YY.MM.DD`
+        format: String,
+    },
+}
+
+impl Scheme {
+    /// The calendar version format, `None` for semantic versions
+    pub fn calendar_format(&self) -> eyre::Result<Option<CalendarFormat>> {
+        match self {
+            Scheme::Semver => Ok(None),
+            Scheme::Calver { format } => format
+                .parse()
+                .map(Some)
+                .wrap_err("The scheme has an invalid calendar version format"),
+        }
+    }
+}
+
+/// A file that contains the version of the project
+#[derive(Deserialize, Serialize, JsonSchema, Debug, PartialEq, Eq)]
 pub struct ProjectFile {
+    /// Path to the file, relative to the subpath
     pub path: String,
+    /// Where the version is in the file, nested keys are separated by dots, e.g. `package.version`.
+    /// The line type takes the line with `{version}` where the version is instead, e.g. `version: {version}`
     pub key: String,
+    /// How the file is parsed
     #[serde(rename = "type")]
     pub project_type: ProjectType,
+    /// The plugin executable for the `exec` type, relative to the subpath if it contains a slash and looked up in the PATH otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// More keys that get a value on every release, e.g. `appVersion` of a Helm chart
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<KeyValue>,
+}
+
+/// Another key of a project file and the value it gets on a release
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct KeyValue {
+    /// Where the value is in the file, like `key` of the file. Lines have `{value}` where the value is
+    pub key: String,
+    /// The value, `{version}` is replaced with the released version and `{tag:<tagprefix>}`
+    /// with the highest stable version released with another tagprefix, e.g. of another package of a monorepo
+    pub value: String,
 }
 
 impl Clone for ProjectFile {
@@ -31,33 +88,286 @@ impl Clone for ProjectFile {
             path: self.path.to_string(),
             key: self.key.to_string(),
             project_type: self.project_type,
+            command: self.command.clone(),
+            values: self.values.clone(),
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Config file of semantic-release
+#[derive(Deserialize, Serialize, JsonSchema, Debug)]
 pub struct Config {
+    /// Prepended to the version to build the tag, e.g. `v` for `v1.2.3`
     pub tagprefix: String,
+    /// Directory of the package inside of the repository, `.` if this isn't a monorepo
     pub subpath: String,
+    /// The files that contain the version of the package
     pub files: Vec<ProjectFile>,
+    /// The commit type a revert is treated as if the reverted commit is not part of the release
+    #[serde(default = "default_revert_type")]
+    pub revert_type: String,
+    /// The branches releases are allowed from and their channels, every branch releases stable if this is empty
+    #[serde(default)]
+    pub branches: Vec<Branch>,
+    /// Treat 0.y.z versions as initial development, a breaking change bumps the minor and a feature the patch version then.
+    /// Use the --promote-to-stable flag to release 1.0.0
+    #[serde(default)]
+    pub initial_development: bool,
+    /// Commands that run at certain points of a release
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// What the lint subcommand accepts in commit messages
+    #[serde(default)]
+    pub commits: Commits,
+    /// Turns references to issues and pull requests in the release notes into links
+    #[serde(default)]
+    pub references: Vec<ReferenceLink>,
+    /// How the versions are numbered, semantic versions if this is missing
+    #[serde(default)]
+    pub scheme: Scheme,
+    /// The changelog file relative to the subpath, every release adds its notes at the top. Nothing is written without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+}
+
+/// A link for every reference starting with `prefix`
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ReferenceLink {
+    /// `#` for issue and pull request numbers or a project key like `PROJ-`
+    pub prefix: String,
+    /// The link, `{id}` gets replaced with the reference without a leading `#`
+    pub url: String,
+}
+
+/// What the lint subcommand accepts in commit messages
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct Commits {
+    /// The allowed commit types, `feat` and `fix` are the ones that lead to a release
+    #[serde(default = "default_types")]
+    pub types: Vec<String>,
+    /// The allowed scopes, any scope is fine if this is empty
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+impl Default for Commits {
+    fn default() -> Self {
+        Commits {
+            types: default_types(),
+            scopes: Vec::new(),
+        }
+    }
+}
+
+/// Commands that run at certain points of a release.
+/// They run with the system shell in the subpath and get the release as `SR_*` environment variables, a failing command aborts the release.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
+pub struct Hooks {
+    /// Before any file gets changed
+    #[serde(default)]
+    pub pre_release: Vec<String>,
+    /// After the files got the new version and before the commit, other files the commands change get committed as well
+    #[serde(default)]
+    pub post_version_update: Vec<String>,
+    /// After the release commit got created
+    #[serde(default)]
+    pub post_commit: Vec<String>,
+    /// After the release commit got tagged
+    #[serde(default)]
+    pub post_tag: Vec<String>,
+}
+
+/// The versions a maintenance branch may release, `1.x` or `1.2.x`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceRange {
+    pub major: usize,
+    pub minor: Option<usize>,
+}
+
+impl FromStr for MaintenanceRange {
+    type Err = String;
+
+    fn from_str(range: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "{} is not a valid maintenance range like 1.x or 1.2.x",
+                range
+            )
+        };
+        let parts: Vec<&str> = range.split('.').collect();
+        let number = |part: &str| part.parse::<usize>().map_err(|_| invalid());
+        match parts.as_slice() {
+            [major, "x"] | [major, "x", "x"] => Ok(MaintenanceRange {
+                major: number(major)?,
+                minor: None,
+            }),
+            [major, minor, "x"] => Ok(MaintenanceRange {
+                major: number(major)?,
+                minor: Some(number(minor)?),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.minor {
+            Some(minor) => write!(f, "{}.{}.x", self.major, minor),
+            None => write!(f, "{}.x", self.major),
+        }
+    }
+}
+
+/// What kind of releases a branch produces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Maintenance(MaintenanceRange),
+    /// Pre-releases with the given identifier, e.g. `beta` for `1.3.0-beta.1`
+    Prerelease(String),
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(try_from = "RawBranch", into = "RawBranch")]
+pub struct Branch {
+    /// Glob pattern matched against the branch name
+    pub name: String,
+    pub channel: Channel,
+}
+
+/// What kind of releases a branch produces
+#[derive(Deserialize, Serialize, JsonSchema, Default)]
+#[serde(rename_all = "lowercase")]
+enum ChannelKind {
+    /// Regular versions
+    #[default]
+    Stable,
+    /// Versions inside of `range` that weren't released from another branch yet
+    Maintenance,
+    /// Versions like `1.3.0-beta.1` with the `prerelease` identifier
+    Prerelease,
+}
+
+/// A branch releases are allowed from, a plain string is a shorthand for a stable branch
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "Branch")]
+enum RawBranch {
+    /// Glob pattern matched against the branch name
+    Name(String),
+    Table {
+        /// Glob pattern matched against the branch name
+        name: String,
+        #[serde(default)]
+        channel: ChannelKind,
+        /// The versions a maintenance branch may release, `1.x` or `1.2.x`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        range: Option<String>,
+        /// The pre-release identifier, e.g. `beta`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        prerelease: Option<String>,
+    },
+}
+
+// the config contains a `RawBranch` that gets validated into a `Branch`
+impl JsonSchema for Branch {
+    fn schema_name() -> Cow<'static, str> {
+        RawBranch::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        RawBranch::json_schema(generator)
+    }
+}
+
+impl From<Branch> for RawBranch {
+    fn from(branch: Branch) -> Self {
+        let name = branch.name;
+        match branch.channel {
+            Channel::Stable => RawBranch::Name(name),
+            Channel::Maintenance(range) => RawBranch::Table {
+                name,
+                channel: ChannelKind::Maintenance,
+                range: Some(range.to_string()),
+                prerelease: None,
+            },
+            Channel::Prerelease(prerelease) => RawBranch::Table {
+                name,
+                channel: ChannelKind::Prerelease,
+                range: None,
+                prerelease: Some(prerelease),
+            },
+        }
+    }
+}
+
+impl TryFrom<RawBranch> for Branch {
+    type Error = String;
+
+    fn try_from(raw: RawBranch) -> Result<Self, Self::Error> {
+        let (name, channel, range, prerelease) = match raw {
+            RawBranch::Name(name) => {
+                return Ok(Branch {
+                    name,
+                    channel: Channel::Stable,
+                })
+            }
+            RawBranch::Table {
+                name,
+                channel,
+                range,
+                prerelease,
+            } => (name, channel, range, prerelease),
+        };
+        let channel = match (channel, range, prerelease) {
+            (ChannelKind::Stable, None, None) => Channel::Stable,
+            (ChannelKind::Maintenance, Some(range), None) => Channel::Maintenance(range.parse()?),
+            (ChannelKind::Prerelease, None, Some(prerelease)) => {
+                if prerelease.is_empty() || prerelease.contains(['.', '-']) {
+                    return Err(format!(
+                        "Branch {}: the pre-release identifier {:?} must not be empty or contain dots or dashes",
+                        name, prerelease
+                    ));
+                }
+                Channel::Prerelease(prerelease)
+            }
+            (ChannelKind::Maintenance, None, _) => {
+                return Err(format!("Branch {}: maintenance branches need a range", name))
+            }
+            (ChannelKind::Prerelease, _, None) => {
+                return Err(format!(
+                    "Branch {}: pre-release branches need a prerelease identifier",
+                    name
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "Branch {}: range is only allowed for maintenance and prerelease only for prerelease branches",
+                    name
+                ))
+            }
+        };
+        Ok(Branch { name, channel })
+    }
+}
+
+fn default_revert_type() -> String {
+    "fix".to_string()
+}
+
+fn default_types() -> Vec<String> {
+    [
+        "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore",
+        "revert",
+    ]
+    .map(str::to_string)
+    .to_vec()
 }
 
 impl Config {
-    pub fn from_path(path: &str) -> eyre::Result<Config> {
-        let path = Path::new(path);
-        let path = fs::canonicalize(path)
-            .wrap_err_with(|| format!("Failed to turn {} into a valid path", &path.display()))
-            .suggestion("If the file doesn't exist you can create it with the --init flag")?;
-        info!("Parsing config file {:?}", style(&path).bold());
-        let file = fs::read_to_string(&path)
-            .wrap_err_with(|| format!("failed to read config file {:?}", &path))
-            .suggestion("If the file doesn't exist you can create it with the --init flag")?;
-
-        let config = file.parse::<Document>().unwrap();
-        let config: Config = toml_edit::de::from_document(config)
-            .wrap_err_with(|| format!("Failed to parse config file {:?}", &path))?;
-
-        debug!("Parsed config: {:?}", config);
-        Ok(config)
+    /// The JSON Schema of the config file
+    pub fn schema() -> schemars::Schema {
+        schemars::schema_for!(Config)
     }
 }
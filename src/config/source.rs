@@ -0,0 +1,336 @@
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use console::style;
+use serde_json::Value;
+use tracing::{debug, info, instrument};
+
+use super::Config;
+
+/// Dedicated config files, checked before the manifests
+const CONFIG_FILES: &[&str] = &["project.toml", ".semantic-release.toml"];
+
+/// Manifests that may contain the config in a section of their own
+const EMBEDDED: &[(&str, &[&str])] = &[
+    ("Cargo.toml", &["package", "metadata", "semantic-release"]),
+    ("Cargo.toml", &["tool", "semantic-release"]),
+    ("pyproject.toml", &["tool", "semantic-release"]),
+    ("package.json", &["semantic-release"]),
+];
+
+/// Settings that override the config file, a flag wins over its environment variable
+#[derive(Args, Debug, Default, Clone)]
+pub struct Overrides {
+    /// Override the tagprefix of the config file
+    #[arg(long, env = "SEMANTIC_RELEASE_TAGPREFIX")]
+    pub tagprefix: Option<String>,
+    /// Override the subpath of the config file
+    #[arg(long, env = "SEMANTIC_RELEASE_SUBPATH")]
+    pub subpath: Option<String>,
+    /// Override the revert_type of the config file
+    #[arg(long, env = "SEMANTIC_RELEASE_REVERT_TYPE")]
+    pub revert_type: Option<String>,
+}
+
+impl Overrides {
+    fn entries(&self) -> [(&'static str, &'static str, Option<&String>); 3] {
+        [
+            (
+                "tagprefix",
+                "SEMANTIC_RELEASE_TAGPREFIX",
+                self.tagprefix.as_ref(),
+            ),
+            ("subpath", "SEMANTIC_RELEASE_SUBPATH", self.subpath.as_ref()),
+            (
+                "revert_type",
+                "SEMANTIC_RELEASE_REVERT_TYPE",
+                self.revert_type.as_ref(),
+            ),
+        ]
+    }
+
+    /// Where each overridden setting came from, e.g. `tagprefix from --tagprefix`
+    pub fn describe(&self) -> Vec<String> {
+        self.entries()
+            .into_iter()
+            .filter_map(|(key, variable, value)| {
+                let value = value?;
+                let origin = match env::var(variable) {
+                    Ok(from_env) if &from_env == value => variable.to_string(),
+                    _ => format!("--{}", key.replace('_', "-")),
+                };
+                Some(format!("{} from {}", key, origin))
+            })
+            .collect()
+    }
+
+    /// Whether `key` is overridden, the value of the config file doesn't matter then
+    pub fn overrides(&self, key: &str) -> bool {
+        self.entries()
+            .iter()
+            .any(|(overridden, _, value)| *overridden == key && value.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().iter().all(|(_, _, value)| value.is_none())
+    }
+
+    pub fn apply(&self, config: &mut Value) {
+        let Value::Object(config) = config else {
+            return;
+        };
+        for (key, _, value) in self.entries() {
+            if let Some(value) = value {
+                config.insert(key.to_string(), Value::String(value.clone()));
+            }
+        }
+    }
+}
+
+/// Where the config was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    /// The keys leading to the config if it is part of a manifest
+    pub section: Option<&'static [&'static str]>,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(section) = self.section {
+            write!(f, " [{}]", section.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+impl ConfigSource {
+    /// The directory the subpath is relative to
+    pub fn directory(&self) -> eyre::Result<PathBuf> {
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::canonicalize(directory)
+            .wrap_err_with(|| format!("Failed to resolve {}", directory.display()))
+    }
+
+    fn is_json(&self) -> bool {
+        self.path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+    }
+
+    /// Reads the file, without the surrounding manifest for embedded configs
+    pub fn read(&self) -> eyre::Result<Value> {
+        let content = fs::read_to_string(&self.path)
+            .wrap_err_with(|| format!("Failed to read config file {}", self.path.display()))
+            .suggestion("If the file doesn't exist you can create it with the --init flag")?;
+        let mut value: Value = if self.is_json() {
+            serde_json::from_str(&content)
+                .wrap_err_with(|| format!("Failed to parse {}", self.path.display()))?
+        } else {
+            toml_edit::de::from_str(&content)
+                .wrap_err_with(|| format!("Failed to parse {}", self.path.display()))?
+        };
+        for key in self.section.unwrap_or_default() {
+            value = match value {
+                Value::Object(mut table) => table.remove(*key),
+                _ => None,
+            }
+            .ok_or_else(|| eyre::eyre!("{} has no {} section", self.path.display(), key))?;
+        }
+        Ok(value)
+    }
+
+    /// The source for an explicitly given path, manifests are read from their section
+    pub fn from_path(path: &Path) -> eyre::Result<ConfigSource> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        let candidates: Vec<&'static [&'static str]> = EMBEDDED
+            .iter()
+            .filter(|(manifest, _)| Some(*manifest) == name.as_deref())
+            .map(|(_, section)| *section)
+            .collect();
+        if candidates.is_empty() {
+            return Ok(ConfigSource {
+                path: path.to_path_buf(),
+                section: None,
+            });
+        }
+        for section in &candidates {
+            let source = ConfigSource {
+                path: path.to_path_buf(),
+                section: Some(section),
+            };
+            if source.read().is_ok() {
+                return Ok(source);
+            }
+        }
+        let sections: Vec<String> = candidates.iter().map(|section| section.join(".")).collect();
+        Err(eyre::eyre!(
+            "{} has no semantic-release config",
+            path.display()
+        ))
+        .suggestion(format!("Add a {} section", sections.join(" or ")))
+    }
+}
+
+fn find_in(directory: &Path) -> Option<ConfigSource> {
+    for name in CONFIG_FILES {
+        let path = directory.join(name);
+        if path.is_file() {
+            return Some(ConfigSource {
+                path,
+                section: None,
+            });
+        }
+    }
+    EMBEDDED.iter().find_map(|(name, section)| {
+        let source = ConfigSource {
+            path: directory.join(name),
+            section: Some(section),
+        };
+        (source.path.is_file() && source.read().is_ok()).then_some(source)
+    })
+}
+
+/// Looks for a config from `start` upwards, stopping at the root of the git repository
+#[instrument(level = "trace")]
+pub fn discover(start: &Path) -> eyre::Result<ConfigSource> {
+    let start = fs::canonicalize(start)?;
+    for directory in start.ancestors() {
+        debug!("Looking for a config in {}", directory.display());
+        if let Some(source) = find_in(directory) {
+            return Ok(source);
+        }
+        if directory.join(".git").exists() {
+            break;
+        }
+    }
+    Err(eyre::eyre!(
+        "Could not find a config file in {} or above",
+        start.display()
+    ))
+    .suggestion(
+        "Pass the path to the config file, or create one with the --init flag. Supported are project.toml, .semantic-release.toml, a [package.metadata.semantic-release] or [tool.semantic-release] section in Cargo.toml, a [tool.semantic-release] section in pyproject.toml and a \"semantic-release\" key in package.json",
+    )
+}
+
+/// The config at the given path or the discovered one
+pub fn locate(path: Option<&str>) -> eyre::Result<ConfigSource> {
+    match path {
+        Some(path) => ConfigSource::from_path(Path::new(path)),
+        None => discover(Path::new(".")),
+    }
+}
+
+/// Finds the config, either at the given path or by discovering it, and layers the overrides on top
+pub fn load(path: Option<&str>, overrides: &Overrides) -> eyre::Result<(Config, ConfigSource)> {
+    let source = locate(path)?;
+    info!("Using the config from {}", style(&source).bold());
+    let mut value = source.read()?;
+    overrides.apply(&mut value);
+    let config: Config = serde_json::from_value(value)
+        .wrap_err_with(|| format!("Failed to parse the config from {}", source))
+        .suggestion("Run the check subcommand to see every problem of the config")?;
+    debug!("Parsed config: {:?}", config);
+    Ok((config, source))
+}
+
+/// The merged config as TOML, preceded by comments saying where it came from
+pub fn show(path: Option<&str>, overrides: &Overrides) -> eyre::Result<String> {
+    let (config, source) = load(path, overrides)?;
+    let mut shown = format!("# from {}\n", source);
+    for overridden in overrides.describe() {
+        shown.push_str(&format!("# {}\n", overridden));
+    }
+    let config =
+        toml_edit::ser::to_string_pretty(&config).wrap_err("Failed to serialize the config")?;
+    shown.push_str(&config);
+    Ok(shown)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const CONFIG: &str = "subpath = \".\"\ntagprefix = \"v\"\nfiles = []\n";
+
+    #[test]
+    fn discovers_config_files_upwards() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("packages/app/src")).unwrap();
+        fs::write(root.join(".semantic-release.toml"), CONFIG).unwrap();
+        // a manifest without a config section doesn't count
+        fs::write(root.join("packages/app/package.json"), "{}").unwrap();
+
+        let source = discover(&root.join("packages/app/src")).unwrap();
+        assert_eq!(source.path.file_name().unwrap(), ".semantic-release.toml");
+        assert_eq!(source.section, None);
+
+        fs::write(
+            root.join("packages/app/package.json"),
+            r#"{"semantic-release": {"subpath": ".", "tagprefix": "app-", "files": []}}"#,
+        )
+        .unwrap();
+        let source = discover(&root.join("packages/app/src")).unwrap();
+        assert_eq!(source.path.file_name().unwrap(), "package.json");
+        let (config, _) = load(
+            Some(&source.path.display().to_string()),
+            &Overrides::default(),
+        )
+        .unwrap();
+        assert_eq!(config.tagprefix, "app-");
+    }
+
+    #[test]
+    fn cargo_metadata_section() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        fs::write(
+            &path,
+            format!(
+                "[package]\nversion = \"1.0.0\"\n\n[package.metadata.semantic-release]\n{}",
+                CONFIG
+            ),
+        )
+        .unwrap();
+        let source = discover(dir.path()).unwrap();
+        assert_eq!(
+            source.to_string(),
+            format!("{} [package.metadata.semantic-release]", path.display())
+        );
+
+        fs::write(&path, "[package]\nversion = \"1.0.0\"\n").unwrap();
+        assert!(ConfigSource::from_path(&path).is_err());
+    }
+
+    #[test]
+    fn overrides_win() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("project.toml");
+        fs::write(&path, CONFIG).unwrap();
+        let overrides = Overrides {
+            tagprefix: Some("release-".to_string()),
+            ..Default::default()
+        };
+        let (config, _) = load(Some(&path.display().to_string()), &overrides).unwrap();
+        assert_eq!(config.tagprefix, "release-");
+        assert_eq!(config.subpath, ".");
+        assert_eq!(overrides.describe(), vec!["tagprefix from --tagprefix"]);
+    }
+}
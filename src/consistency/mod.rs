@@ -0,0 +1,238 @@
+use std::{fmt::Display, fmt::Write, path::Path, path::PathBuf};
+
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{self, ContextCompat, WrapErr},
+    Help,
+};
+use tracing::{debug, info, instrument};
+
+use crate::{
+    config::ProjectFile, project::load_versionfile, semver::SemanticVersion,
+    transaction::FileUpdate,
+};
+
+/// What `--sync` aligns the versions of the configured files to
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncTarget {
+    /// The highest version of the configured files
+    Highest,
+    /// The version of the latest tag with the tagprefix
+    Tag,
+}
+
+/// The version a configured file contains right now
+#[derive(Debug)]
+pub struct FileVersion<V = SemanticVersion> {
+    pub file: ProjectFile,
+    pub filepath: PathBuf,
+    pub version: V,
+}
+
+/// Reads the semantic version of every configured file before anything gets released
+pub fn read_versions(subpath: &Path, files: &[ProjectFile]) -> eyre::Result<Vec<FileVersion>> {
+    read_versions_with(subpath, files, |raw| raw.parse())
+}
+
+/// Reads the version of every configured file, `parse` turns them into versions of the scheme of the package
+#[instrument(level = "trace", skip(files, parse))]
+pub fn read_versions_with<V: Display>(
+    subpath: &Path,
+    files: &[ProjectFile],
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> eyre::Result<Vec<FileVersion<V>>> {
+    files
+        .iter()
+        .map(|file| {
+            let filepath = subpath.join(&file.path);
+            let raw = load_versionfile(&filepath, file)
+                .and_then(|versionfile| versionfile.read_version())
+                .wrap_err_with(|| format!("Failed to read the version of {}", file.path))?;
+            let version = parse(&raw)
+                .wrap_err_with(|| format!("{} contains the invalid version {}", file.path, raw))?;
+            debug!("{} has the version {}", file.path, version);
+            Ok(FileVersion {
+                file: file.clone(),
+                filepath,
+                version,
+            })
+        })
+        .collect()
+}
+
+/// The versions as a table with a column for the file, the key and the version
+pub fn table<V: Display>(versions: &[FileVersion<V>]) -> String {
+    let rows: Vec<[String; 3]> = versions
+        .iter()
+        .map(|version| {
+            [
+                version.file.path.clone(),
+                version.file.key.clone(),
+                version.version.to_string(),
+            ]
+        })
+        .collect();
+    let header = ["file".to_string(), "key".to_string(), "version".to_string()];
+    let widths: Vec<usize> = (0..3)
+        .map(|column| {
+            rows.iter()
+                .chain([&header])
+                .map(|row| row[column].len())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let mut table = String::new();
+    for row in [&header].into_iter().chain(&rows) {
+        let _ = writeln!(
+            table,
+            "{:<file$}  {:<key$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            file = widths[0],
+            key = widths[1]
+        );
+    }
+    table
+}
+
+/// Fails with a table of all versions if the configured files disagree
+pub fn ensure_consistent<V: Display + PartialEq>(versions: &[FileVersion<V>]) -> eyre::Result<()> {
+    let Some(first) = versions.first() else {
+        return Ok(());
+    };
+    if versions
+        .iter()
+        .all(|version| version.version == first.version)
+    {
+        return Ok(());
+    }
+    Err(eyre::eyre!(
+        "The configured files contain different versions:\n{}",
+        table(versions).trim_end()
+    ))
+    .suggestion("Pass --sync highest or --sync tag to align them without a release")
+}
+
+/// The version `--sync` aligns every file to, `released` are the versions that have a tag
+pub fn sync_version<V: Ord + Clone>(
+    target: SyncTarget,
+    versions: &[FileVersion<V>],
+    released: &[V],
+) -> eyre::Result<V> {
+    let version = match target {
+        SyncTarget::Highest => versions
+            .iter()
+            .map(|version| &version.version)
+            .max()
+            .wrap_err("There are no files to sync")?,
+        SyncTarget::Tag => released
+            .iter()
+            .max()
+            .wrap_err("There is no tag with the tagprefix to sync to")
+            .suggestion("Use --sync highest instead")?,
+    };
+    Ok(version.clone())
+}
+
+/// The updates that set every file that differs from `target` to it
+pub fn sync_updates<V: Display + PartialEq>(
+    versions: &[FileVersion<V>],
+    target: &V,
+) -> eyre::Result<Vec<FileUpdate>> {
+    let mut updates = Vec::new();
+    for version in versions {
+        if version.version == *target {
+            debug!("{} is at {} already", version.file.path, target);
+            continue;
+        }
+        info!(
+            "Changing {} from {} to {}",
+            version.file.path, version.version, target
+        );
+        let content = load_versionfile(&version.filepath, &version.file)
+            .and_then(|mut versionfile| versionfile.update_values(&target.to_string(), &[]))
+            .wrap_err_with(|| format!("Failed to update {}", version.file.path))?;
+        updates.push(FileUpdate {
+            filename: version.file.path.clone(),
+            content,
+            version: target.to_string(),
+            file: version.file.clone(),
+        });
+    }
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::config::ProjectType;
+
+    use super::*;
+
+    fn versions() -> (TempDir, Vec<FileVersion>) {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nversion = \"1.2.0\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("package.json"), r#"{"version": "1.10.0"}"#).unwrap();
+        let files = [
+            ProjectFile {
+                path: "Cargo.toml".to_string(),
+                key: "package.version".to_string(),
+                project_type: ProjectType::Toml,
+                command: None,
+                values: Vec::new(),
+            },
+            ProjectFile {
+                path: "package.json".to_string(),
+                key: "version".to_string(),
+                project_type: ProjectType::Json,
+                command: None,
+                values: Vec::new(),
+            },
+        ];
+        let versions = read_versions(dir.path(), &files).unwrap();
+        (dir, versions)
+    }
+
+    #[test]
+    fn reports_differing_versions() {
+        let (_dir, versions) = versions();
+        let err = ensure_consistent(&versions).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The configured files contain different versions:\n\
+             file          key              version\n\
+             Cargo.toml    package.version  1.2.0\n\
+             package.json  version          1.10.0"
+        );
+        assert!(ensure_consistent(&versions[..1]).is_ok());
+    }
+
+    #[test]
+    fn syncs_to_highest_or_tag() {
+        let (_dir, versions) = versions();
+        let highest = sync_version(SyncTarget::Highest, &versions, &[]).unwrap();
+        assert_eq!(highest.to_string(), "1.10.0");
+        let updates = sync_updates(&versions, &highest).unwrap();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].filename, "Cargo.toml");
+        assert!(updates[0].content.contains("version = \"1.10.0\""));
+
+        assert!(sync_version(SyncTarget::Tag, &versions, &[]).is_err());
+        let mut tagged = SemanticVersion::new();
+        tagged.set_version("1.2.0").unwrap();
+        let tag = sync_version(SyncTarget::Tag, &versions, &[tagged]).unwrap();
+        assert_eq!(
+            sync_updates(&versions, &tag).unwrap()[0].filename,
+            "package.json"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{self, WrapErr};
+use tracing::instrument;
+
+use crate::command::{run_command, CommandError};
+use crate::git::{parse_log, Commit, LOG_FORMAT};
+
+use super::{GitBackend, IndexEntry};
+
+/// Runs the git executable from the directory the backend was opened in
+#[derive(Debug)]
+pub struct Cli {
+    directory: PathBuf,
+}
+
+impl Cli {
+    fn git(&self, args: Vec<String>) -> eyre::Result<String> {
+        let output = run_command("git", &self.directory, args)?;
+        let stdout = String::from_utf8(output.stdout).context("git returned invalid UTF-8")?;
+        Ok(stdout)
+    }
+
+    /// Like [Cli::git], but a non-zero exit code is an expected answer and leads to `None`
+    fn git_optional(&self, args: Vec<String>) -> eyre::Result<Option<String>> {
+        match run_command("git", &self.directory, args) {
+            Ok(output) => {
+                let stdout =
+                    String::from_utf8(output.stdout).context("git returned invalid UTF-8")?;
+                Ok(Some(stdout.trim().to_owned()))
+            }
+            Err(CommandError::Failed { .. }) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// git needs forward slashes and a leading ./ to resolve paths relative to the working directory in `<rev>:<path>`
+fn relative_path(path: &Path) -> String {
+    format!("./{}", path.display()).replace('\\', "/")
+}
+
+impl GitBackend for Cli {
+    #[instrument(level = "trace", name = "cli::open")]
+    fn open(directory: &Path) -> eyre::Result<Box<Self>> {
+        Ok(Box::new(Cli {
+            directory: directory.to_path_buf(),
+        }))
+    }
+
+    #[instrument(level = "trace", name = "cli::tags", skip(self))]
+    fn tags(&self) -> eyre::Result<Vec<String>> {
+        let output = self.git(vec!["tag".to_owned(), "--list".to_owned()])?;
+        Ok(output
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect())
+    }
+
+    #[instrument(level = "trace", name = "cli::commits", skip(self))]
+    fn commits(&self, since: Option<&str>, until: &str, path: &Path) -> eyre::Result<Vec<Commit>> {
+        let range = match since {
+            Some(since) => format!("{}..{}", since, until),
+            None => until.to_owned(),
+        };
+        let args = vec![
+            "log".to_owned(),
+            LOG_FORMAT.to_owned(),
+            range,
+            "--".to_owned(),
+            path.display().to_string(),
+        ];
+        let output = self.git(args)?;
+        Ok(parse_log(&output))
+    }
+
+    #[instrument(level = "trace", name = "cli::read_file", skip(self))]
+    fn read_file(&self, revision: &str, path: &Path) -> eyre::Result<String> {
+        let object = format!("{}:{}", revision, relative_path(path));
+        self.git(vec!["show".to_owned(), object])
+    }
+
+    #[instrument(level = "trace", name = "cli::stage", skip(self))]
+    fn stage(&self, path: &Path) -> eyre::Result<()> {
+        self.git(vec![
+            "add".to_owned(),
+            "--".to_owned(),
+            path.display().to_string(),
+        ])?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", name = "cli::index_entry", skip(self))]
+    fn index_entry(&self, path: &Path) -> eyre::Result<Option<IndexEntry>> {
+        let output = self.git(vec![
+            "ls-files".to_owned(),
+            "--stage".to_owned(),
+            "--".to_owned(),
+            path.display().to_string(),
+        ])?;
+        // <mode> <id> <stage>\t<path>
+        let Some(line) = output.lines().next() else {
+            return Ok(None);
+        };
+        let mut fields = line.split_whitespace();
+        let (Some(mode), Some(id)) = (fields.next(), fields.next()) else {
+            eyre::bail!("Unexpected output from git ls-files --stage: {}", line);
+        };
+        let mode = u32::from_str_radix(mode, 8)
+            .wrap_err_with(|| format!("Unexpected file mode {} from git ls-files", mode))?;
+        Ok(Some(IndexEntry {
+            mode,
+            id: id.to_owned(),
+        }))
+    }
+
+    #[instrument(level = "trace", name = "cli::restore_index_entry", skip(self))]
+    fn restore_index_entry(&self, path: &Path, entry: Option<&IndexEntry>) -> eyre::Result<()> {
+        let args = match entry {
+            Some(entry) => {
+                // unlike other paths, the one of --cacheinfo is relative to the root of the worktree
+                let prefix = self.git(vec!["rev-parse".to_owned(), "--show-prefix".to_owned()])?;
+                let path = format!("{}{}", prefix.trim(), path.display()).replace('\\', "/");
+                vec![
+                    "update-index".to_owned(),
+                    "--add".to_owned(),
+                    "--cacheinfo".to_owned(),
+                    format!("{:o},{},{}", entry.mode, entry.id, path),
+                ]
+            }
+            None => vec![
+                "update-index".to_owned(),
+                "--force-remove".to_owned(),
+                "--".to_owned(),
+                path.display().to_string(),
+            ],
+        };
+        self.git(args)?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", name = "cli::commit", skip(self))]
+    fn commit(&self, message: &str) -> eyre::Result<String> {
+        self.git(vec![
+            "commit".to_owned(),
+            "-m".to_owned(),
+            message.to_owned(),
+        ])?;
+        self.head()
+    }
+
+    #[instrument(level = "trace", name = "cli::tag", skip(self))]
+    fn tag(&self, name: &str) -> eyre::Result<()> {
+        self.git(vec!["tag".to_owned(), name.to_owned()])?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", name = "cli::changed_files", skip(self))]
+    fn changed_files(&self) -> eyre::Result<Vec<PathBuf>> {
+        let toplevel = self.git(vec!["rev-parse".to_owned(), "--show-toplevel".to_owned()])?;
+        let toplevel = PathBuf::from(toplevel.trim());
+        let output = self.git(vec![
+            "status".to_owned(),
+            "--porcelain".to_owned(),
+            "-z".to_owned(),
+            "--untracked-files=all".to_owned(),
+        ])?;
+        let mut files = Vec::new();
+        let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+        while let Some(entry) = entries.next() {
+            let (status, path) = entry.split_at(3.min(entry.len()));
+            files.push(toplevel.join(path));
+            // renames and copies are followed by the original path
+            if status.starts_with('R') || status.starts_with('C') {
+                if let Some(source) = entries.next() {
+                    files.push(toplevel.join(source));
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    #[instrument(level = "trace", name = "cli::head", skip(self))]
+    fn head(&self) -> eyre::Result<String> {
+        let id = self.git(vec!["rev-parse".to_owned(), "HEAD".to_owned()])?;
+        Ok(id.trim().to_owned())
+    }
+
+    #[instrument(level = "trace", name = "cli::current_branch", skip(self))]
+    fn current_branch(&self) -> eyre::Result<Option<String>> {
+        self.git_optional(vec![
+            "symbolic-ref".to_owned(),
+            "--quiet".to_owned(),
+            "--short".to_owned(),
+            "HEAD".to_owned(),
+        ])
+    }
+
+    #[instrument(level = "trace", name = "cli::operation_in_progress", skip(self))]
+    fn operation_in_progress(&self) -> eyre::Result<Option<String>> {
+        let git_dir = self.git(vec![
+            "rev-parse".to_owned(),
+            "--absolute-git-dir".to_owned(),
+        ])?;
+        let git_dir = Path::new(git_dir.trim());
+        let markers = [
+            ("rebase-merge", "rebase"),
+            ("rebase-apply", "rebase"),
+            ("MERGE_HEAD", "merge"),
+            ("CHERRY_PICK_HEAD", "cherry-pick"),
+            ("REVERT_HEAD", "revert"),
+            ("BISECT_LOG", "bisect"),
+        ];
+        let operation = markers
+            .iter()
+            .find(|(marker, _)| git_dir.join(marker).exists())
+            .map(|(_, operation)| operation.to_string());
+        Ok(operation)
+    }
+
+    #[instrument(level = "trace", name = "cli::upstream_divergence", skip(self))]
+    fn upstream_divergence(&self) -> eyre::Result<Option<(usize, usize)>> {
+        let upstream = self.git_optional(vec![
+            "rev-parse".to_owned(),
+            "--symbolic-full-name".to_owned(),
+            "@{upstream}".to_owned(),
+        ])?;
+        if upstream.is_none() {
+            return Ok(None);
+        }
+        let counts = self.git(vec![
+            "rev-list".to_owned(),
+            "--left-right".to_owned(),
+            "--count".to_owned(),
+            "HEAD...@{upstream}".to_owned(),
+        ])?;
+        let counts: Vec<usize> = counts
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .context("Failed to parse the output of git rev-list --count")?;
+        match counts.as_slice() {
+            [ahead, behind] => Ok(Some((*ahead, *behind))),
+            _ => eyre::bail!("Unexpected output from git rev-list --count: {:?}", counts),
+        }
+    }
+
+    #[instrument(level = "trace", name = "cli::committer", skip(self))]
+    fn committer(&self) -> eyre::Result<Option<String>> {
+        let ident = self.git_optional(vec!["var".to_owned(), "GIT_COMMITTER_IDENT".to_owned()])?;
+        // the identity is followed by a timestamp and timezone
+        Ok(ident.map(|ident| match ident.rfind('>') {
+            Some(end) => ident[..=end].to_owned(),
+            None => ident,
+        }))
+    }
+
+    #[instrument(level = "trace", name = "cli::hooks_directory", skip(self))]
+    fn hooks_directory(&self) -> eyre::Result<PathBuf> {
+        let hooks = self.git(vec![
+            "rev-parse".to_owned(),
+            "--path-format=absolute".to_owned(),
+            "--git-path".to_owned(),
+            "hooks".to_owned(),
+        ])?;
+        Ok(PathBuf::from(hooks.trim()))
+    }
+}
@@ -0,0 +1,498 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, ContextCompat, WrapErr};
+use gix::{
+    bstr::{BString, ByteSlice},
+    hash::ObjectId,
+    index::entry::{Flags, Mode, Stat},
+    refs::transaction::PreviousValue,
+    remote::Direction,
+    state::InProgress,
+    status::UntrackedFiles,
+    Repository,
+};
+use tracing::{debug, instrument};
+
+use crate::git::Commit;
+
+use super::{GitBackend, IndexEntry};
+
+/// Talks to the repository in-process through gitoxide
+pub struct Gitoxide {
+    repo: Repository,
+    /// The directory the backend was opened in, relative to the root of the worktree
+    prefix: PathBuf,
+}
+
+impl Gitoxide {
+    /// Turns a path relative to the directory the backend was opened in into one relative to the root of the worktree
+    fn repo_path(&self, path: &Path) -> eyre::Result<PathBuf> {
+        let mut result = PathBuf::new();
+        for component in self.prefix.join(path).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !result.pop() {
+                        eyre::bail!("{} points outside of the repository", path.display());
+                    }
+                }
+                Component::Normal(part) => result.push(part),
+                Component::RootDir | Component::Prefix(_) => {
+                    eyre::bail!("{} has to be a relative path", path.display())
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Runs `action` and adds what it was doing in which repository to its errors, the ones of gitoxide only describe the failing detail
+    fn context<T>(
+        &self,
+        operation: &str,
+        action: impl FnOnce() -> eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        action().wrap_err_with(|| {
+            let path = self.repo.workdir().unwrap_or_else(|| self.repo.git_dir());
+            format!(
+                "Failed to {} in the git repository at {}",
+                operation,
+                path.display()
+            )
+        })
+    }
+
+    fn resolve(&self, revision: &str) -> eyre::Result<ObjectId> {
+        let id = self
+            .repo
+            .rev_parse_single(revision)
+            .wrap_err_with(|| format!("Failed to resolve revision {}", revision))?
+            .object()?
+            .peel_to_commit()
+            .wrap_err_with(|| format!("{} doesn't point to a commit", revision))?
+            .id;
+        Ok(id)
+    }
+
+    /// Id of the tree or blob at `path` in the given commit, the root tree for an empty path
+    fn entry_id(&self, commit: ObjectId, path: &Path) -> eyre::Result<Option<ObjectId>> {
+        let tree = self.repo.find_commit(commit)?.tree()?;
+        if path.as_os_str().is_empty() {
+            return Ok(Some(tree.id));
+        }
+        let entry = tree.lookup_entry_by_path(path)?;
+        Ok(entry.map(|entry| entry.object_id()))
+    }
+
+    /// The path of a file in the index, always with forward slashes
+    fn index_path(&self, path: &Path) -> eyre::Result<BString> {
+        let repo_path = self.repo_path(path)?;
+        Ok(BString::from(
+            gix::path::into_bstr(&repo_path).replace("\\", "/"),
+        ))
+    }
+
+    fn head_id(&self) -> eyre::Result<ObjectId> {
+        Ok(self
+            .repo
+            .head_id()
+            .context("Failed to resolve HEAD")?
+            .detach())
+    }
+
+    fn commit_time(&self, id: ObjectId) -> eyre::Result<i64> {
+        Ok(self.repo.find_commit(id)?.time()?.seconds)
+    }
+
+    /// Number of commits reachable from `tip` but not from `hidden`
+    fn count_commits(&self, tip: ObjectId, hidden: ObjectId) -> eyre::Result<usize> {
+        let walk = self.repo.rev_walk([tip]).with_hidden([hidden]).all()?;
+        let mut count = 0;
+        for info in walk {
+            info?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl GitBackend for Gitoxide {
+    #[instrument(level = "trace", name = "gitoxide::open")]
+    fn open(directory: &Path) -> eyre::Result<Box<Self>> {
+        let repo = gix::discover(directory).wrap_err_with(|| {
+            format!("Failed to find a git repository at {}", directory.display())
+        })?;
+        let workdir = repo.workdir().wrap_err_with(|| {
+            format!(
+                "Bare repositories are not supported, {} is one",
+                repo.git_dir().display()
+            )
+        })?;
+        let workdir = workdir
+            .canonicalize()
+            .wrap_err_with(|| format!("Failed to resolve {}", workdir.display()))?;
+        let directory = directory
+            .canonicalize()
+            .wrap_err_with(|| format!("Failed to resolve {}", directory.display()))?;
+        let prefix = directory
+            .strip_prefix(&workdir)
+            .wrap_err_with(|| {
+                format!(
+                    "{} is not part of the worktree at {}",
+                    directory.display(),
+                    workdir.display()
+                )
+            })?
+            .to_path_buf();
+        debug!(
+            "Opened repository at {:?} with prefix {:?}",
+            workdir, prefix
+        );
+        Ok(Box::new(Gitoxide { repo, prefix }))
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::tags", skip(self))]
+    fn tags(&self) -> eyre::Result<Vec<String>> {
+        self.context("list the tags", || {
+            let references = self.repo.references()?;
+            let mut tags = Vec::new();
+            for reference in references.tags()? {
+                let reference = reference.map_err(|err| eyre::eyre!(err))?;
+                tags.push(reference.name().shorten().to_string());
+            }
+            Ok(tags)
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::commits", skip(self))]
+    fn commits(&self, since: Option<&str>, until: &str, path: &Path) -> eyre::Result<Vec<Commit>> {
+        self.context(
+            &format!("list the commits that touched {}", path.display()),
+            || {
+                let path = self.repo_path(path)?;
+                let mut hidden = HashSet::new();
+                if let Some(since) = since {
+                    for info in self.repo.rev_walk([self.resolve(since)?]).all()? {
+                        hidden.insert(info?.id);
+                    }
+                }
+
+                // the same history simplification `git log -- <path>` does by default: a commit that has the same
+                // content at `path` as one of its parents is left out and only that parent is followed,
+                // so merges only show up if they changed `path` compared to every parent
+                let until = self.resolve(until)?;
+                let mut seen = HashSet::from([until]);
+                let mut queue = BinaryHeap::new();
+                let mut order = 0;
+                if !hidden.contains(&until) {
+                    queue.push((self.commit_time(until)?, Reverse(order), until));
+                }
+                let mut commits = Vec::new();
+                while let Some((_, _, id)) = queue.pop() {
+                    let commit = self.repo.find_commit(id)?;
+                    let parents: Vec<ObjectId> =
+                        commit.parent_ids().map(|id| id.detach()).collect();
+                    let entry = self.entry_id(id, &path)?;
+                    let mut same_as = Vec::new();
+                    for parent in &parents {
+                        same_as.push(self.entry_id(*parent, &path)? == entry);
+                    }
+                    // hidden parents don't simplify the history, just like in git
+                    let relevant: Vec<bool> =
+                        parents.iter().map(|id| !hidden.contains(id)).collect();
+                    let followed = parents
+                        .iter()
+                        .zip(&same_as)
+                        .zip(&relevant)
+                        .find(|((_, same), relevant)| **same && **relevant)
+                        .map(|((parent, _), _)| vec![*parent])
+                        .unwrap_or_else(|| parents.clone());
+                    for parent in followed {
+                        if !hidden.contains(&parent) && seen.insert(parent) {
+                            order += 1;
+                            queue.push((self.commit_time(parent)?, Reverse(order), parent));
+                        }
+                    }
+
+                    let touched = if parents.is_empty() {
+                        entry.is_some()
+                    } else if relevant.contains(&true) {
+                        !same_as
+                            .iter()
+                            .zip(&relevant)
+                            .any(|(same, relevant)| *same && *relevant)
+                    } else {
+                        !same_as.contains(&true)
+                    };
+                    if !touched {
+                        continue;
+                    }
+                    let message = commit.message_raw()?.to_str_lossy();
+                    commits.push(Commit {
+                        id: id.to_string(),
+                        author: commit.author()?.name.to_string(),
+                        message: message.trim().to_string(),
+                    });
+                }
+                Ok(commits)
+            },
+        )
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::read_file", skip(self))]
+    fn read_file(&self, revision: &str, path: &Path) -> eyre::Result<String> {
+        let repo_path = self.repo_path(path)?;
+        let id = self
+            .entry_id(self.resolve(revision)?, &repo_path)?
+            .wrap_err_with(|| format!("{} doesn't exist in {}", path.display(), revision))?;
+        let blob = self.repo.find_blob(id)?;
+        let content = String::from_utf8(blob.data.clone())
+            .wrap_err_with(|| format!("{} is not valid UTF-8", path.display()))?;
+        Ok(content)
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::stage", skip(self))]
+    fn stage(&self, path: &Path) -> eyre::Result<()> {
+        self.context(&format!("stage {}", path.display()), || {
+            let repo_path = self.repo_path(path)?;
+            let workdir = self
+                .repo
+                .workdir()
+                .context("Bare repositories are not supported")?;
+            let filepath = workdir.join(&repo_path);
+            let content = std::fs::read(&filepath)
+                .wrap_err_with(|| format!("Failed to read {}", filepath.display()))?;
+            let id = self.repo.write_blob(content)?.detach();
+            let metadata = gix::index::fs::Metadata::from_path_no_follow(&filepath)?;
+            let stat = Stat::from_fs(&metadata)?;
+            // like git, the executable bit is ignored with core.fileMode = false
+            let file_mode = self
+                .repo
+                .config_snapshot()
+                .boolean("core.fileMode")
+                .unwrap_or(true);
+            let mode = if file_mode && metadata.is_executable() {
+                Mode::FILE_EXECUTABLE
+            } else {
+                Mode::FILE
+            };
+
+            let index_path = self.index_path(path)?;
+            let mut index = self.repo.open_index()?;
+            match index.entry_index_by_path(index_path.as_bstr()) {
+                Ok(position) => {
+                    let entry = &mut index.entries_mut()[position];
+                    entry.id = id;
+                    entry.stat = stat;
+                    if file_mode {
+                        entry.mode = mode;
+                    }
+                }
+                Err(_) => {
+                    index.dangerously_push_entry(
+                        stat,
+                        id,
+                        Flags::empty(),
+                        mode,
+                        index_path.as_bstr(),
+                    );
+                    index.sort_entries();
+                }
+            }
+            // the cached trees are outdated now, git would happily commit them otherwise
+            index.remove_tree();
+            index.write(Default::default())?;
+            Ok(())
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::index_entry", skip(self))]
+    fn index_entry(&self, path: &Path) -> eyre::Result<Option<IndexEntry>> {
+        self.context(
+            &format!("read the index entry of {}", path.display()),
+            || {
+                let index_path = self.index_path(path)?;
+                let index = self.repo.open_index()?;
+                let entry = index
+                    .entry_by_path(index_path.as_bstr())
+                    .map(|entry| IndexEntry {
+                        mode: entry.mode.bits(),
+                        id: entry.id.to_string(),
+                    });
+                Ok(entry)
+            },
+        )
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::restore_index_entry", skip(self))]
+    fn restore_index_entry(&self, path: &Path, entry: Option<&IndexEntry>) -> eyre::Result<()> {
+        self.context(
+            &format!("restore the index entry of {}", path.display()),
+            || {
+                let index_path = self.index_path(path)?;
+                let mut index = self.repo.open_index()?;
+                index.remove_entries(|_, entry_path, _| entry_path == index_path.as_bstr());
+                if let Some(entry) = entry {
+                    let mode = Mode::from_bits(entry.mode)
+                        .wrap_err_with(|| format!("Unknown file mode {:o}", entry.mode))?;
+                    // without stat information git compares the content again
+                    index.dangerously_push_entry(
+                        Stat::default(),
+                        ObjectId::from_hex(entry.id.as_bytes())?,
+                        Flags::empty(),
+                        mode,
+                        index_path.as_bstr(),
+                    );
+                    index.sort_entries();
+                }
+                index.remove_tree();
+                index.write(Default::default())?;
+                Ok(())
+            },
+        )
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::commit", skip(self))]
+    fn commit(&self, message: &str) -> eyre::Result<String> {
+        self.context("commit", || {
+            let index = self.repo.open_index()?;
+            let mut editor = self
+                .repo
+                .edit_tree(ObjectId::empty_tree(self.repo.object_hash()))?;
+            for entry in index.entries() {
+                let mode = entry
+                    .mode
+                    .to_tree_entry_mode()
+                    .wrap_err("Found an index entry that can't be committed")?;
+                editor.upsert(entry.path(&index), mode.kind(), entry.id)?;
+            }
+            let tree = editor.write()?;
+            let id = self
+                .repo
+                .commit("HEAD", message, tree, [self.head_id()?])
+                .context(
+                "Failed to create the commit, make sure user.name and user.email are configured",
+            )?;
+            Ok(id.to_string())
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::tag", skip(self))]
+    fn tag(&self, name: &str) -> eyre::Result<()> {
+        self.context(&format!("create the tag {}", name), || {
+            // gitoxide accepts an existing tag as long as it points at the same commit, git doesn't
+            let reference = format!("refs/tags/{}", name);
+            if self.repo.try_find_reference(reference.as_str())?.is_some() {
+                eyre::bail!("The tag {} already exists", name);
+            }
+            self.repo
+                .tag_reference(name, self.head_id()?, PreviousValue::MustNotExist)?;
+            Ok(())
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::changed_files", skip(self))]
+    fn changed_files(&self) -> eyre::Result<Vec<PathBuf>> {
+        self.context("list the changed files", || {
+            let workdir = self
+                .repo
+                .workdir()
+                .context("Bare repositories are not supported")?;
+            let status = self
+                .repo
+                .status(gix::progress::Discard)?
+                .untracked_files(UntrackedFiles::Files)
+                .into_iter(Vec::<BString>::new())?;
+            let mut files = Vec::new();
+            for item in status {
+                let item = item?;
+                files.push(workdir.join(gix::path::from_bstr(item.location())));
+            }
+            Ok(files)
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::head", skip(self))]
+    fn head(&self) -> eyre::Result<String> {
+        self.context("resolve HEAD", || Ok(self.head_id()?.to_string()))
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::current_branch", skip(self))]
+    fn current_branch(&self) -> eyre::Result<Option<String>> {
+        self.context("read the current branch", || {
+            let name = self.repo.head_name()?;
+            Ok(name.map(|name| name.shorten().to_string()))
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::operation_in_progress", skip(self))]
+    fn operation_in_progress(&self) -> eyre::Result<Option<String>> {
+        self.context("check for unfinished operations", || {
+            let operation = self.repo.state().map(|state| match state {
+                InProgress::ApplyMailbox => "am",
+                InProgress::ApplyMailboxRebase
+                | InProgress::Rebase
+                | InProgress::RebaseInteractive => "rebase",
+                InProgress::Bisect => "bisect",
+                InProgress::CherryPick | InProgress::CherryPickSequence => "cherry-pick",
+                InProgress::Merge => "merge",
+                InProgress::Revert | InProgress::RevertSequence => "revert",
+            });
+            Ok(operation.map(str::to_owned))
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::upstream_divergence", skip(self))]
+    fn upstream_divergence(&self) -> eyre::Result<Option<(usize, usize)>> {
+        self.context("compare HEAD with its upstream branch", || {
+            let Some(branch) = self.repo.head_name()? else {
+                return Ok(None);
+            };
+            let Some(upstream) = self
+                .repo
+                .branch_remote_tracking_ref_name(branch.as_ref(), Direction::Fetch)
+            else {
+                return Ok(None);
+            };
+            let upstream = upstream?;
+            let Some(mut upstream) = self.repo.try_find_reference(upstream.as_ref())? else {
+                return Ok(None);
+            };
+            let upstream = upstream.peel_to_id()?.detach();
+            let head = self.head_id()?;
+            let ahead = self.count_commits(head, upstream)?;
+            let behind = self.count_commits(upstream, head)?;
+            Ok(Some((ahead, behind)))
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::committer", skip(self))]
+    fn committer(&self) -> eyre::Result<Option<String>> {
+        self.context("read the committer identity", || {
+            let Some(committer) = self.repo.committer() else {
+                return Ok(None);
+            };
+            let committer = committer?;
+            Ok(Some(format!("{} <{}>", committer.name, committer.email)))
+        })
+    }
+
+    #[instrument(level = "trace", name = "gitoxide::hooks_directory", skip(self))]
+    fn hooks_directory(&self) -> eyre::Result<PathBuf> {
+        self.context("find the hooks directory", || {
+            let config = self.repo.config_snapshot();
+            let Some(path) = config.trusted_path("core.hooksPath") else {
+                return Ok(self.repo.common_dir().canonicalize()?.join("hooks"));
+            };
+            // relative paths are relative to the root of the worktree, like git runs the hooks
+            let workdir = self
+                .repo
+                .workdir()
+                .context("Bare repositories are not supported")?;
+            Ok(workdir.canonicalize()?.join(path?))
+        })
+    }
+}
@@ -0,0 +1,349 @@
+mod cli;
+mod gitoxide;
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use color_eyre::eyre;
+use tracing::instrument;
+
+use super::Commit;
+
+use self::{cli::Cli, gitoxide::Gitoxide};
+
+/// Every interaction with the git repository goes through this.
+///
+/// Paths are relative to the directory the backend was opened in, just like they would be when running the git CLI from there.
+pub trait GitBackend {
+    fn open(directory: &Path) -> eyre::Result<Box<Self>>
+    where
+        Self: Sized;
+    /// Names of all tags in the repository, without the `refs/tags/` prefix
+    fn tags(&self) -> eyre::Result<Vec<String>>;
+    /// Commits reachable from `until` but not from `since` that touched `path`, newest first
+    fn commits(&self, since: Option<&str>, until: &str, path: &Path) -> eyre::Result<Vec<Commit>>;
+    /// Content of the file at `path` as of `revision`
+    fn read_file(&self, revision: &str, path: &Path) -> eyre::Result<String>;
+    /// Add the current content of the file at `path` to the index
+    fn stage(&self, path: &Path) -> eyre::Result<()>;
+    /// What the index holds for the file at `path`, `None` if it isn't in the index
+    fn index_entry(&self, path: &Path) -> eyre::Result<Option<IndexEntry>>;
+    /// Puts an entry returned by [GitBackend::index_entry] back into the index, removes the file from it for `None`
+    fn restore_index_entry(&self, path: &Path, entry: Option<&IndexEntry>) -> eyre::Result<()>;
+    /// Commit the index on top of HEAD, returns the id of the new commit
+    fn commit(&self, message: &str) -> eyre::Result<String>;
+    /// Create a lightweight tag pointing at HEAD
+    fn tag(&self, name: &str) -> eyre::Result<()>;
+    /// Absolute paths of all files with uncommitted changes, untracked files included
+    fn changed_files(&self) -> eyre::Result<Vec<PathBuf>>;
+    /// Id of the commit HEAD points at
+    fn head(&self) -> eyre::Result<String>;
+    /// Name of the checked out branch, `None` if HEAD is detached
+    fn current_branch(&self) -> eyre::Result<Option<String>>;
+    /// Name of an unfinished operation like a rebase or merge
+    fn operation_in_progress(&self) -> eyre::Result<Option<String>>;
+    /// How many commits HEAD is ahead and behind of its upstream branch, `None` without an upstream
+    fn upstream_divergence(&self) -> eyre::Result<Option<(usize, usize)>>;
+    /// The identity new commits are created with, `None` if it isn't configured
+    fn committer(&self) -> eyre::Result<Option<String>>;
+    /// Absolute path of the directory git runs the hooks from, `core.hooksPath` included
+    fn hooks_directory(&self) -> eyre::Result<PathBuf>;
+}
+
+/// A file in the index, enough to restore it after staging something else
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The file mode like `0o100644`
+    pub mode: u32,
+    /// Id of the staged blob
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackendKind {
+    /// Use the gitoxide library, doesn't need a git installation
+    Gitoxide,
+    /// Shell out to the git executable, this runs the usual git hooks on commit
+    Cli,
+}
+
+#[instrument(level = "trace")]
+pub fn open_backend(kind: BackendKind, directory: &Path) -> eyre::Result<Box<dyn GitBackend>> {
+    match kind {
+        BackendKind::Gitoxide => Ok(Gitoxide::open(directory)?),
+        BackendKind::Cli => Ok(Cli::open(directory)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use tempfile::TempDir;
+
+    use crate::testing::{self, commit, git};
+
+    use super::*;
+
+    /// A repo with a tagged initial commit and a package in `pkg` with one commit after the tag
+    fn fixture() -> TempDir {
+        let dir = testing::repo();
+        let path = dir.path();
+        fs::create_dir(path.join("pkg")).unwrap();
+        fs::write(path.join("pkg/version"), "1.0.0").unwrap();
+        fs::write(path.join("README"), "readme").unwrap();
+        commit(path, "chore: initial");
+        git(path, &["tag", "v1.0.0"]);
+        fs::write(path.join("README"), "more readme").unwrap();
+        commit(path, "docs: readme");
+        fs::write(path.join("pkg/file"), "content").unwrap();
+        commit(path, "feat: add file\n\nwith a body");
+        dir
+    }
+
+    fn backends(directory: &Path) -> Vec<Box<dyn GitBackend>> {
+        vec![
+            open_backend(BackendKind::Gitoxide, directory).unwrap(),
+            open_backend(BackendKind::Cli, directory).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn list_tags() {
+        let repo = fixture();
+        for backend in backends(repo.path()) {
+            assert_eq!(backend.tags().unwrap(), vec!["v1.0.0".to_string()]);
+        }
+    }
+
+    #[test]
+    fn errors_name_the_operation_and_repository() {
+        let repo = fixture();
+        let backend = open_backend(BackendKind::Gitoxide, repo.path()).unwrap();
+        let error = backend
+            .commits(Some("v9.9.9"), "HEAD", Path::new("."))
+            .unwrap_err();
+        let message = format!("{:?}", error);
+        assert!(
+            message.contains("list the commits that touched ."),
+            "{}",
+            message
+        );
+        assert!(message.contains("v9.9.9"), "{}", message);
+        let root = repo.path().canonicalize().unwrap();
+        let root = root.file_name().unwrap().to_str().unwrap();
+        assert!(message.contains(root), "{}", message);
+    }
+
+    #[test]
+    fn read_file_at_revision() {
+        let repo = fixture();
+        let directory = repo.path().join("pkg");
+        fs::write(directory.join("version"), "2.0.0\n").unwrap();
+        commit(repo.path(), "feat: new version");
+        for backend in backends(&directory) {
+            assert_eq!(
+                backend.read_file("v1.0.0", Path::new("version")).unwrap(),
+                "1.0.0"
+            );
+            assert_eq!(
+                backend.read_file("HEAD", Path::new("version")).unwrap(),
+                "2.0.0\n"
+            );
+            assert_eq!(
+                backend.read_file("HEAD", Path::new("../README")).unwrap(),
+                "more readme"
+            );
+            assert!(backend.read_file("v1.0.0", Path::new("file")).is_err());
+        }
+    }
+
+    #[test]
+    fn commits_are_filtered_by_path() {
+        let repo = fixture();
+        for backend in backends(&repo.path().join("pkg")) {
+            let commits = backend
+                .commits(Some("v1.0.0"), "HEAD", Path::new("."))
+                .unwrap();
+            let messages: Vec<&str> = commits.iter().map(|c| c.message.as_str()).collect();
+            assert_eq!(messages, vec!["feat: add file\n\nwith a body"]);
+            assert_eq!(commits[0].author, "Test");
+
+            let commits = backend.commits(None, "HEAD", Path::new("..")).unwrap();
+            let summaries: Vec<&str> = commits.iter().map(|c| c.summary()).collect();
+            assert_eq!(
+                summaries,
+                vec!["feat: add file", "docs: readme", "chore: initial"]
+            );
+        }
+
+        // a merge doesn't touch the package if it has the same content as one of its parents
+        git(repo.path(), &["checkout", "--quiet", "-b", "side"]);
+        fs::write(repo.path().join("pkg/file"), "side").unwrap();
+        commit(repo.path(), "fix: on the side");
+        git(repo.path(), &["checkout", "--quiet", "main"]);
+        fs::write(repo.path().join("README"), "main readme").unwrap();
+        commit(repo.path(), "docs: on main");
+        git(
+            repo.path(),
+            &[
+                "merge",
+                "--quiet",
+                "--no-ff",
+                "-m",
+                "Merge branch side",
+                "side",
+            ],
+        );
+        for backend in backends(&repo.path().join("pkg")) {
+            let commits = backend
+                .commits(Some("v1.0.0"), "HEAD", Path::new("."))
+                .unwrap();
+            let mut summaries: Vec<&str> = commits.iter().map(|c| c.summary()).collect();
+            summaries.sort();
+            assert_eq!(summaries, vec!["feat: add file", "fix: on the side"]);
+
+            let commits = backend
+                .commits(Some("v1.0.0"), "HEAD", Path::new(".."))
+                .unwrap();
+            let mut summaries: Vec<&str> = commits.iter().map(|c| c.summary()).collect();
+            summaries.sort();
+            assert_eq!(
+                summaries,
+                vec![
+                    "Merge branch side",
+                    "docs: on main",
+                    "docs: readme",
+                    "feat: add file",
+                    "fix: on the side"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn stage_commit_and_tag() {
+        for kind in [BackendKind::Gitoxide, BackendKind::Cli] {
+            let repo = fixture();
+            let directory = repo.path().join("pkg");
+            let backend = open_backend(kind, &directory).unwrap();
+            fs::write(directory.join("version"), "1.1.0").unwrap();
+            backend.stage(Path::new("version")).unwrap();
+            let id = backend.commit("release 1.1.0").unwrap();
+            assert_eq!(backend.head().unwrap(), id);
+            backend.tag("v1.1.0").unwrap();
+
+            let commits = backend
+                .commits(Some("v1.0.0"), "HEAD", Path::new("."))
+                .unwrap();
+            assert_eq!(commits.first().unwrap().id, id);
+            assert_eq!(commits.first().unwrap().message, "release 1.1.0");
+            assert_eq!(git(repo.path(), &["show", "v1.1.0:pkg/version"]), "1.1.0");
+            assert_eq!(git(repo.path(), &["show", "v1.1.0:pkg/file"]), "content");
+            assert!(backend.tag("v1.1.0").is_err());
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let script = directory.join("file");
+                fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+                backend.stage(Path::new("file")).unwrap();
+                let output = git(repo.path(), &["ls-files", "--stage", "pkg/file"]);
+                assert!(
+                    output.starts_with("100755 "),
+                    "{:?} staged {}",
+                    kind,
+                    output
+                );
+                backend.commit("make file executable").unwrap();
+            }
+
+            // the git CLI has to agree that everything got committed
+            let status = git(repo.path(), &["status", "--porcelain"]);
+            assert!(status.is_empty(), "{:?} left changes behind", kind);
+        }
+    }
+
+    #[test]
+    fn restore_the_index() {
+        for kind in [BackendKind::Gitoxide, BackendKind::Cli] {
+            let repo = fixture();
+            let directory = repo.path().join("pkg");
+            let backend = open_backend(kind, &directory).unwrap();
+            fs::write(directory.join("version"), "1.0.1").unwrap();
+            git(&directory, &["add", "version"]);
+            let staged = backend.index_entry(Path::new("version")).unwrap();
+            assert!(staged.is_some());
+            assert_eq!(backend.index_entry(Path::new("new")).unwrap(), None);
+
+            fs::write(directory.join("version"), "1.1.0").unwrap();
+            fs::write(directory.join("new"), "new").unwrap();
+            backend.stage(Path::new("version")).unwrap();
+            backend.stage(Path::new("new")).unwrap();
+            backend
+                .restore_index_entry(Path::new("version"), staged.as_ref())
+                .unwrap();
+            backend.restore_index_entry(Path::new("new"), None).unwrap();
+
+            let staged = git(repo.path(), &["show", ":pkg/version"]);
+            assert_eq!(staged, "1.0.1", "{:?}", kind);
+            let status = git(repo.path(), &["status", "--porcelain"]);
+            assert_eq!(status, "MM pkg/version\n?? pkg/new", "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn repository_state() {
+        let repo = fixture();
+        fs::write(repo.path().join("pkg/version"), "2.0.0").unwrap();
+        fs::write(repo.path().join("untracked"), "").unwrap();
+        let root = repo.path().canonicalize().unwrap();
+        for backend in backends(&repo.path().join("pkg")) {
+            let mut changed = backend.changed_files().unwrap();
+            changed.sort();
+            assert_eq!(
+                changed,
+                vec![root.join("pkg/version"), root.join("untracked")]
+            );
+            assert_eq!(backend.current_branch().unwrap(), Some("main".to_string()));
+            assert_eq!(backend.operation_in_progress().unwrap(), None);
+            assert_eq!(backend.upstream_divergence().unwrap(), None);
+            assert_eq!(
+                backend.committer().unwrap(),
+                Some("Test <test@example.com>".to_string())
+            );
+            assert_eq!(backend.hooks_directory().unwrap(), root.join(".git/hooks"));
+        }
+        git(repo.path(), &["config", "core.hooksPath", "tools/hooks"]);
+        for backend in backends(&repo.path().join("pkg")) {
+            assert_eq!(backend.hooks_directory().unwrap(), root.join("tools/hooks"));
+        }
+    }
+
+    #[test]
+    fn upstream_divergence() {
+        let origin = fixture();
+        let clone = TempDir::new().unwrap();
+        let origin_path = origin.path().to_str().unwrap();
+        git(clone.path(), &["clone", "--quiet", origin_path, "."]);
+        git(
+            origin.path(),
+            &["commit", "--quiet", "--allow-empty", "-m", "one"],
+        );
+        git(
+            origin.path(),
+            &["commit", "--quiet", "--allow-empty", "-m", "two"],
+        );
+        git(clone.path(), &["fetch", "--quiet"]);
+        git(clone.path(), &["config", "user.name", "Test"]);
+        git(clone.path(), &["config", "user.email", "test@example.com"]);
+        git(
+            clone.path(),
+            &["commit", "--quiet", "--allow-empty", "-m", "local"],
+        );
+        for backend in backends(clone.path()) {
+            assert_eq!(backend.upstream_divergence().unwrap(), Some((1, 2)));
+        }
+    }
+}
@@ -1,14 +1,262 @@
+pub mod backend;
+
+use clap::ValueEnum;
 use console::style;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, instrument};
 
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy)]
+/// Format string for `git log` that yields one record per commit, see [parse_log]
+pub const LOG_FORMAT: &str = "--format=%H%n%an%n%B%x00";
+
+/// Start of the summary of every commit semantic-release creates
+pub const RELEASE_COMMIT_PREFIX: &str = "[Semantic release]";
+
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BumpLevel {
+    #[value(skip)]
     None,
     Patch,
     Minor,
     Major,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    /// Name of the author
+    pub author: String,
+    pub message: String,
+}
+
+/// Trailer keys that introduce references to issues, e.g. `Closes #123` or `Refs: PROJ-456`
+const REFERENCE_KEYS: [&str; 13] = [
+    "close",
+    "closes",
+    "closed",
+    "fix",
+    "fixes",
+    "fixed",
+    "resolve",
+    "resolves",
+    "resolved",
+    "ref",
+    "refs",
+    "references",
+    "see",
+];
+
+fn is_reference_key(key: &str) -> bool {
+    REFERENCE_KEYS
+        .iter()
+        .any(|reference_key| key.trim().eq_ignore_ascii_case(reference_key))
+}
+
+/// The parts of a summary following the commit template, `<type>(<scope>): <description>`
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conventional<'a> {
+    pub kind: Option<&'a str>,
+    pub scope: Option<&'a str>,
+    /// The whole summary if it doesn't follow the template, without the `(#78)` suffix of squash merges
+    pub description: &'a str,
+}
+
+/// Prefixes of standards and encodings that look like issue keys, e.g. `UTF-8` or `ISO-8601`
+const NOT_PROJECTS: [&str; 5] = ["ISO", "RFC", "SHA", "UCS", "UTF"];
+
+/// Whether `token` looks like `#123` or a key like `PROJ-456`
+fn is_reference(token: &str) -> bool {
+    let is_number = |number: &str| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+    if let Some(number) = token.strip_prefix('#') {
+        return is_number(number);
+    }
+    token.split_once('-').is_some_and(|(project, number)| {
+        project.starts_with(|c: char| c.is_ascii_uppercase())
+            && !NOT_PROJECTS.contains(&project)
+            && project
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            && is_number(number)
+    })
+}
+
+/// Splits the `(#78)` suffix squash merges add off the summary
+fn split_pull_request(summary: &str) -> (&str, Option<&str>) {
+    let pull_request = summary
+        .trim_end()
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .filter(|(_, reference)| reference.starts_with('#') && is_reference(reference));
+    match pull_request {
+        Some((rest, reference)) => (rest.trim_end(), Some(reference)),
+        None => (summary, None),
+    }
+}
+
+impl Commit {
+    pub fn summary(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+
+    /// Whether this is a release or sync commit created by semantic-release
+    pub fn is_release(&self) -> bool {
+        self.summary().starts_with(RELEASE_COMMIT_PREFIX)
+    }
+
+    /// Splits the summary into type, scope and description
+    pub fn conventional(&self) -> Conventional<'_> {
+        let (summary, _) = split_pull_request(self.summary());
+        let parsed = summary.split_once(':').and_then(|(prefix, description)| {
+            let prefix = prefix.trim_end_matches('!');
+            let (kind, scope) = match prefix.split_once('(') {
+                Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+                None => (prefix, None),
+            };
+            let is_type = !kind.is_empty() && kind.chars().all(char::is_alphanumeric);
+            is_type.then_some(Conventional {
+                kind: Some(kind),
+                scope,
+                description: description.trim(),
+            })
+        });
+        parsed.unwrap_or(Conventional {
+            kind: None,
+            scope: None,
+            description: summary,
+        })
+    }
+
+    /// Names of the people credited with `Co-authored-by:` trailers
+    pub fn co_authors(&self) -> Vec<&str> {
+        self.message
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim()
+                    .eq_ignore_ascii_case("co-authored-by")
+                    .then(|| value.split('<').next().unwrap_or_default().trim())
+            })
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    /// References to issues and pull requests, like `#123` or `PROJ-456`, in order and without duplicates.
+    /// They come from the `(#78)` suffix of squash merges and trailers like `Closes #123` or `Refs: PROJ-456`
+    /// in the last paragraph of the body.
+    pub fn references(&self) -> Vec<&str> {
+        let (_, pull_request) = split_pull_request(self.summary());
+        let trailers = self.trailer_lines().flat_map(|line| {
+            // `Refs: PROJ-456` or `Closes #123`, the value of a key without colon has to start with the reference
+            let value = match line.split_once(':') {
+                Some((key, value)) if is_reference_key(key) => Some(value),
+                _ => line
+                    .split_once(char::is_whitespace)
+                    .filter(|(key, value)| is_reference_key(key) && value.starts_with('#'))
+                    .map(|(_, value)| value),
+            };
+            value
+                .unwrap_or_default()
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|token| token.trim_end_matches(['.', ';', ':', '!', '?', ')']))
+                .filter(|token| is_reference(token))
+        });
+        let mut references: Vec<&str> = Vec::new();
+        for reference in pull_request.into_iter().chain(trailers) {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+        references
+    }
+
+    /// The lines of the last paragraph of the body, where git puts the trailers
+    fn trailer_lines(&self) -> impl Iterator<Item = &str> {
+        let mut paragraphs: Vec<Vec<&str>> = vec![Vec::new()];
+        for line in self.message.lines().map(str::trim) {
+            match paragraphs.last_mut() {
+                Some(paragraph) if !line.is_empty() => paragraph.push(line),
+                Some(paragraph) if !paragraph.is_empty() => paragraphs.push(Vec::new()),
+                _ => {}
+            }
+        }
+        paragraphs.retain(|paragraph| !paragraph.is_empty());
+        // the first paragraph is the summary
+        let trailers = if paragraphs.len() > 1 {
+            paragraphs.pop()
+        } else {
+            None
+        };
+        trailers.into_iter().flatten()
+    }
+
+    /// Returns the (possibly abbreviated) id of the commit this one reverts,
+    /// as long as the message contains the line generated by `git revert`
+    pub fn reverted_commit(&self) -> Option<&str> {
+        self.message.lines().find_map(|line| {
+            let id = line.trim().strip_prefix("This reverts commit ")?;
+            let id = id.trim_end_matches('.');
+            let is_id = id.len() >= 7 && id.chars().all(|c| c.is_ascii_hexdigit());
+            is_id.then_some(id)
+        })
+    }
+}
+
+/// Parses the output of `git log` called with [LOG_FORMAT]
+pub fn parse_log(output: &str) -> Vec<Commit> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let record = record.trim_start_matches('\n');
+            let mut parts = record.splitn(3, '\n');
+            let id = parts.next().unwrap_or_default();
+            if id.is_empty() {
+                return None;
+            }
+            Some(Commit {
+                id: id.to_string(),
+                author: parts.next().unwrap_or_default().to_string(),
+                message: parts.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Removes every revert commit together with the commit it reverts, as long as both are part of `commits`.
+/// Expects the commits newest first (like `git log` returns them) so reverts of reverts cancel out properly.
+/// Reverts of commits outside of `commits` are kept.
+#[instrument(level = "trace", skip(commits))]
+pub fn drop_reverted(commits: Vec<Commit>) -> Vec<Commit> {
+    let mut cancelled: Vec<usize> = Vec::new();
+    for (index, commit) in commits.iter().enumerate() {
+        if cancelled.contains(&index) {
+            continue;
+        }
+        let Some(reverted) = commit.reverted_commit() else {
+            continue;
+        };
+        let target = commits
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .find(|(i, c)| !cancelled.contains(i) && c.id.starts_with(reverted));
+        if let Some((target_index, target)) = target {
+            debug!(
+                "{} reverts {}, ignoring both",
+                style(commit.summary()).bold(),
+                style(target.summary()).bold()
+            );
+            cancelled.push(index);
+            cancelled.push(target_index);
+        }
+    }
+    commits
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !cancelled.contains(i))
+        .map(|(_, commit)| commit)
+        .collect()
+}
+
 #[instrument(level = "trace")]
 fn summary_to_bumplevel(summary: &str) -> BumpLevel {
     let mut bump_level = BumpLevel::None;
@@ -39,10 +287,32 @@ fn summary_to_bumplevel(summary: &str) -> BumpLevel {
     bump_level
 }
 
-pub fn calc_bumplevel(commits: &[&str]) -> BumpLevel {
+/// The bumplevel of a single commit, reverts are treated like a commit of type `revert_type`
+pub fn commit_to_bumplevel(commit: &Commit, revert_type: &str) -> BumpLevel {
+    let mut lines = commit.message.lines();
+    let summary = lines.next().unwrap_or_default();
+    let summary_level = if commit.reverted_commit().is_some() {
+        debug!(
+            "{} reverts a commit from an earlier release, treating it as {}",
+            style(summary).bold(),
+            style(revert_type).bold()
+        );
+        summary_to_bumplevel(revert_type)
+    } else {
+        summary_to_bumplevel(summary)
+    };
+    lines
+        .map(summary_to_bumplevel)
+        .fold(summary_level, BumpLevel::max)
+}
+
+/// Calculates the bumplevel of the given commits.
+/// Revert commits are treated like a commit of type `revert_type`, call [drop_reverted] first
+/// to get rid of reverts that cancel out a commit in the same list.
+pub fn calc_bumplevel(commits: &[Commit], revert_type: &str) -> BumpLevel {
     let mut bumplevels: Vec<BumpLevel> = commits
         .iter()
-        .map(|commit| summary_to_bumplevel(commit))
+        .map(|commit| commit_to_bumplevel(commit, revert_type))
         .collect();
     bumplevels.sort();
     *bumplevels.last().expect("Failed to get last element from bumplevels list; Most likely calc_bumplevel was called on an empty list")
@@ -52,10 +322,140 @@ pub fn calc_bumplevel(commits: &[&str]) -> BumpLevel {
 mod tests {
     use crate::git::*;
 
+    fn commit(id: &str, message: &str) -> Commit {
+        Commit {
+            id: id.to_string(),
+            author: "Test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
     #[test]
     fn bumplevel_comparison() {
         assert!(BumpLevel::None < BumpLevel::Patch);
         assert!(BumpLevel::Patch < BumpLevel::Minor);
         assert!(BumpLevel::Minor < BumpLevel::Major);
     }
+
+    #[test]
+    fn parse_log_records() {
+        let output = "aaaaaaa1\nTest\nfeat: one\n\nbody\n\0\nbbbbbbb2\nTest\nfix: two\n\0\n";
+        let commits = parse_log(output);
+        assert_eq!(
+            commits,
+            vec![
+                commit("aaaaaaa1", "feat: one\n\nbody"),
+                commit("bbbbbbb2", "fix: two")
+            ]
+        );
+    }
+
+    #[test]
+    fn revert_cancels_reverted_commit() {
+        let commits = vec![
+            commit(
+                "bbbbbbb2",
+                "Revert \"feat: one\"\n\nThis reverts commit aaaaaaa1aaaa.",
+            ),
+            commit("cccccc3", "fix: three"),
+            commit("aaaaaaa1aaaa", "feat: one"),
+        ];
+        let commits = drop_reverted(commits);
+        assert_eq!(commits, vec![commit("cccccc3", "fix: three")]);
+        assert_eq!(calc_bumplevel(&commits, "fix"), BumpLevel::Patch);
+    }
+
+    #[test]
+    fn revert_of_revert_restores_commit() {
+        let commits = vec![
+            commit(
+                "ccccccc3",
+                "Revert \"Revert \"feat: one\"\"\n\nThis reverts commit bbbbbbb2.",
+            ),
+            commit(
+                "bbbbbbb2",
+                "Revert \"feat: one\"\n\nThis reverts commit aaaaaaa1.",
+            ),
+            commit("aaaaaaa1", "feat: one"),
+        ];
+        let commits = drop_reverted(commits);
+        assert_eq!(commits, vec![commit("aaaaaaa1", "feat: one")]);
+    }
+
+    #[test]
+    fn lone_revert_uses_revert_type() {
+        let commits = vec![commit(
+            "bbbbbbb2",
+            "Revert \"feat: one\"\n\nThis reverts commit aaaaaaa1.",
+        )];
+        let commits = drop_reverted(commits);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(calc_bumplevel(&commits, "fix"), BumpLevel::Patch);
+        assert_eq!(calc_bumplevel(&commits, "feat"), BumpLevel::Minor);
+        assert_eq!(calc_bumplevel(&commits, "chore"), BumpLevel::None);
+    }
+
+    #[test]
+    fn conventional_parts() {
+        let scoped = commit("a", "feat(cli): add notes\n\nbody");
+        assert_eq!(
+            scoped.conventional(),
+            Conventional {
+                kind: Some("feat"),
+                scope: Some("cli"),
+                description: "add notes"
+            }
+        );
+        let free_form = commit("a", "Update the readme: typos");
+        assert_eq!(free_form.conventional().kind, None);
+        assert_eq!(
+            free_form.conventional().description,
+            "Update the readme: typos"
+        );
+
+        let co_authored = commit(
+            "a",
+            "fix: two\n\nCo-authored-by: Jane Doe <jane@example.com>\nco-authored-by: Max <max@example.com>",
+        );
+        assert_eq!(co_authored.co_authors(), vec!["Jane Doe", "Max"]);
+    }
+
+    #[test]
+    fn references() {
+        let squashed = commit(
+            "a",
+            "fix(api): timeouts (#78)\n\nCloses #12, #13\nRefs: PROJ-456 see the ticket\nfixes #12\nThe #99 in a sentence is ignored",
+        );
+        assert_eq!(squashed.conventional().description, "timeouts");
+        assert_eq!(squashed.references(), vec!["#78", "#12", "#13", "PROJ-456"]);
+
+        let plain = commit("a", "feat: support (#ab) and (Jira)\n\nRefs: proj-1, #x");
+        assert_eq!(plain.conventional().description, "support (#ab) and (Jira)");
+        assert!(plain.references().is_empty());
+
+        let references = |message| -> Vec<String> {
+            let commit = commit("a", message);
+            commit
+                .references()
+                .into_iter()
+                .map(str::to_string)
+                .collect()
+        };
+        assert!(references("Fix the crash when the server closes #12 sockets").is_empty());
+        assert!(
+            references("fix: a crash\n\nFix the crash when the server closes #12 sockets")
+                .is_empty()
+        );
+        assert!(references("fix: encoding\n\nRefs: UTF-8").is_empty());
+        assert_eq!(references("fix: a crash\n\nCloses #12."), vec!["#12"]);
+        assert_eq!(
+            references("fix: a crash\n\nRefs: PROJ-7; #3)"),
+            vec!["PROJ-7", "#3"]
+        );
+        // only the last paragraph holds trailers
+        assert_eq!(
+            references("feat: a feature\n\nCloses #5 in the body\n\nRefs: #6\n"),
+            vec!["#6"]
+        );
+    }
 }
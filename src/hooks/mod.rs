@@ -0,0 +1,184 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, WrapErr};
+use console::style;
+use tracing::{info, instrument};
+
+use crate::{command::run_shell, config::Hooks, git::BumpLevel};
+
+/// The points of a release the hooks run at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    PreRelease,
+    PostVersionUpdate,
+    PostCommit,
+    PostTag,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Stage::PreRelease => "pre_release",
+            Stage::PostVersionUpdate => "post_version_update",
+            Stage::PostCommit => "post_commit",
+            Stage::PostTag => "post_tag",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Everything the hooks get to know about the release
+#[derive(Debug, Clone)]
+pub struct ReleaseContext {
+    pub previous_version: String,
+    pub next_version: String,
+    pub previous_tag: String,
+    pub tag: String,
+    pub bumplevel: BumpLevel,
+    pub commit_message: String,
+    /// The directory the hooks run in
+    pub subpath: PathBuf,
+    /// Id of the release commit once it exists
+    pub commit: Option<String>,
+}
+
+impl ReleaseContext {
+    /// The environment variables the hooks get
+    pub fn env(&self, stage: Stage) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("SR_HOOK", stage.to_string()),
+            ("SR_PREVIOUS_VERSION", self.previous_version.clone()),
+            ("SR_NEXT_VERSION", self.next_version.clone()),
+            ("SR_PREVIOUS_TAG", self.previous_tag.clone()),
+            ("SR_TAG", self.tag.clone()),
+            (
+                "SR_BUMP_LEVEL",
+                format!("{:?}", self.bumplevel).to_lowercase(),
+            ),
+            ("SR_COMMIT_MESSAGE", self.commit_message.clone()),
+            ("SR_SUBPATH", self.subpath.display().to_string()),
+        ];
+        if let Some(commit) = &self.commit {
+            env.push(("SR_COMMIT", commit.clone()));
+        }
+        env
+    }
+}
+
+fn commands(hooks: &Hooks, stage: Stage) -> &[String] {
+    match stage {
+        Stage::PreRelease => &hooks.pre_release,
+        Stage::PostVersionUpdate => &hooks.post_version_update,
+        Stage::PostCommit => &hooks.post_commit,
+        Stage::PostTag => &hooks.post_tag,
+    }
+}
+
+/// Runs the commands of `stage` one after the other, stopping at the first one that fails
+#[instrument(level = "trace", skip(hooks, context))]
+pub fn run(hooks: &Hooks, stage: Stage, context: &ReleaseContext) -> eyre::Result<()> {
+    let env = context.env(stage);
+    for command in commands(hooks, stage) {
+        info!("Running the {} hook {}", stage, style(command).bold());
+        run_shell(command, &context.subpath, &env)
+            .wrap_err_with(|| format!("The {} hook failed", stage))?;
+    }
+    Ok(())
+}
+
+/// Lists the commands of `stage` instead of running them, for dry runs
+pub fn announce(hooks: &Hooks, stage: Stage) {
+    for command in commands(hooks, stage) {
+        info!(
+            "Dry run is active, not running the {} hook {}",
+            stage,
+            style(command).bold()
+        );
+    }
+}
+
+/// `path` relative to `base`, both have to be absolute
+pub fn relative_path(base: &Path, path: &Path) -> PathBuf {
+    let base: Vec<Component> = base
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+    let path: Vec<Component> = path.components().collect();
+    let common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn context(subpath: &Path) -> ReleaseContext {
+        ReleaseContext {
+            previous_version: "1.0.0".to_string(),
+            next_version: "1.1.0".to_string(),
+            previous_tag: "v1.0.0".to_string(),
+            tag: "v1.1.0".to_string(),
+            bumplevel: BumpLevel::Minor,
+            commit_message: "[Semantic release]: Release 1.1.0".to_string(),
+            subpath: subpath.to_path_buf(),
+            commit: None,
+        }
+    }
+
+    #[test]
+    fn hooks_get_the_release_context() {
+        let dir = TempDir::new().unwrap();
+        let hooks = Hooks {
+            post_version_update: vec![
+                "echo \"$SR_HOOK $SR_PREVIOUS_TAG $SR_TAG $SR_BUMP_LEVEL\" > hook.txt".to_string(),
+            ],
+            ..Default::default()
+        };
+        run(&hooks, Stage::PreRelease, &context(dir.path())).unwrap();
+        assert!(!dir.path().join("hook.txt").exists());
+        run(&hooks, Stage::PostVersionUpdate, &context(dir.path())).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("hook.txt")).unwrap(),
+            "post_version_update v1.0.0 v1.1.0 minor\n"
+        );
+    }
+
+    #[test]
+    fn failing_hook_stops() {
+        let dir = TempDir::new().unwrap();
+        let hooks = Hooks {
+            pre_release: vec!["false".to_string(), "touch ran.txt".to_string()],
+            ..Default::default()
+        };
+        let err = run(&hooks, Stage::PreRelease, &context(dir.path())).unwrap_err();
+        assert_eq!(err.to_string(), "The pre_release hook failed");
+        assert!(!dir.path().join("ran.txt").exists());
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(
+                Path::new("/repo/./packages/app"),
+                Path::new("/repo/Cargo.lock")
+            ),
+            Path::new("../../Cargo.lock")
+        );
+        assert_eq!(
+            relative_path(Path::new("/repo"), Path::new("/repo/docs/api.md")),
+            Path::new("docs/api.md")
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::{debug, instrument};
+
+use crate::{
+    config::{ProjectFile, ProjectType},
+    project::load_versionfile,
+    semver::SemanticVersion,
+};
+
+/// Manifests the tool knows about and the keys their version may be at, the first key that resolves wins
+const MANIFESTS: &[(&str, ProjectType, &[&str])] = &[
+    ("package.json", ProjectType::Json, &["version"]),
+    ("composer.json", ProjectType::Json, &["version"]),
+    ("Cargo.toml", ProjectType::Toml, &["package.version"]),
+    (
+        "pyproject.toml",
+        ProjectType::Toml,
+        &["project.version", "tool.poetry.version"],
+    ),
+];
+
+/// Directories that never contain a package of their own
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+/// How deep the recursive scan looks for manifests
+const MAX_DEPTH: usize = 4;
+
+/// A manifest with a version that can be released
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// Path relative to the scanned directory
+    pub path: PathBuf,
+    pub key: String,
+    pub project_type: ProjectType,
+    pub version: String,
+}
+
+impl Manifest {
+    /// The name of the type in the config file
+    pub fn type_name(&self) -> &'static str {
+        match self.project_type {
+            ProjectType::Json => "json",
+            ProjectType::Toml => "toml",
+            ProjectType::Line => "line",
+            ProjectType::Exec => "exec",
+        }
+    }
+}
+
+/// The version at the first of `keys` that resolves to a valid one
+fn read_version(
+    filepath: &Path,
+    project_type: ProjectType,
+    keys: &[&str],
+) -> Option<(String, String)> {
+    keys.iter().find_map(|key| {
+        let file = ProjectFile {
+            path: filepath.display().to_string(),
+            key: key.to_string(),
+            project_type,
+            command: None,
+            values: Vec::new(),
+        };
+        let version = load_versionfile(filepath, &file)
+            .and_then(|versionfile| versionfile.read_version())
+            .ok()?;
+        SemanticVersion::new().set_version(&version).ok()?;
+        Some((key.to_string(), version))
+    })
+}
+
+fn scan(root: &Path, relative: &Path, depth: usize, recursive: bool, found: &mut Vec<Manifest>) {
+    let directory = root.join(relative);
+    for (name, project_type, keys) in MANIFESTS {
+        let filepath = directory.join(name);
+        if !filepath.is_file() {
+            continue;
+        }
+        match read_version(&filepath, *project_type, keys) {
+            Some((key, version)) => found.push(Manifest {
+                path: relative.join(name),
+                key,
+                project_type: *project_type,
+                version,
+            }),
+            None => debug!("{} has no version, skipping it", filepath.display()),
+        }
+    }
+    if !recursive || depth >= MAX_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(&directory) else {
+        return;
+    };
+    let mut subdirectories: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.file_name())
+        .filter(|name| {
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&name.as_ref())
+        })
+        .map(|name| relative.join(name))
+        .collect();
+    subdirectories.sort();
+    for subdirectory in subdirectories {
+        scan(root, &subdirectory, depth + 1, recursive, found);
+    }
+}
+
+/// Finds the manifests in `root` that contain a version, `recursive` also looks into its subdirectories
+#[instrument(level = "trace")]
+pub fn detect_manifests(root: &Path, recursive: bool) -> Vec<Manifest> {
+    let mut found = Vec::new();
+    scan(root, Path::new(""), 0, recursive, &mut found);
+    found
+}
+
+/// The part of `tag` before its version, if it ends in one
+fn tag_prefix(tag: &str) -> Option<&str> {
+    tag.char_indices()
+        .filter(|(_, c)| c.is_ascii_digit())
+        .map(|(i, _)| i)
+        .find(|i| SemanticVersion::new().set_version(&tag[*i..]).is_ok())
+        .map(|i| &tag[..i])
+}
+
+/// Suggests a tagprefix from the existing tags.
+/// A prefix that already has a tag for one of the `versions` is preferred, as releasing starts from that tag.
+pub fn suggest_tagprefix(tags: &[String], versions: &[&str]) -> Option<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in tags {
+        let Some(prefix) = tag_prefix(tag) else {
+            continue;
+        };
+        if versions
+            .iter()
+            .any(|version| tag[prefix.len()..] == **version)
+        {
+            return Some(prefix.to_string());
+        }
+        *counts.entry(prefix).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    counts.first().map(|(prefix, _)| prefix.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn detects_manifests_with_versions() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(root.join("package.json"), r#"{"version": "1.2.0"}"#).unwrap();
+        // a workspace manifest without a version of its own
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        fs::create_dir_all(root.join("crates/core")).unwrap();
+        fs::write(
+            root.join("crates/core/pyproject.toml"),
+            "[tool.poetry]\nversion = \"0.3.1\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("node_modules/dep")).unwrap();
+        fs::write(
+            root.join("node_modules/dep/package.json"),
+            r#"{"version": "9.9.9"}"#,
+        )
+        .unwrap();
+
+        let found = detect_manifests(root, false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path, Path::new("package.json"));
+        assert_eq!(found[0].version, "1.2.0");
+
+        let found = detect_manifests(root, true);
+        let paths: Vec<&Path> = found
+            .iter()
+            .map(|manifest| manifest.path.as_path())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("package.json"),
+                Path::new("crates/core/pyproject.toml")
+            ]
+        );
+        assert_eq!(found[1].key, "tool.poetry.version");
+    }
+
+    #[test]
+    fn suggests_tagprefix() {
+        let tags: Vec<String> = ["v1.0.0", "v1.1.0", "core-2.0.0", "latest"]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect();
+        assert_eq!(suggest_tagprefix(&tags, &["3.0.0"]), Some("v".to_string()));
+        assert_eq!(
+            suggest_tagprefix(&tags, &["2.0.0"]),
+            Some("core-".to_string())
+        );
+        assert_eq!(suggest_tagprefix(&[], &["1.0.0"]), None);
+    }
+}
@@ -1,22 +1,195 @@
-use std::{fs::File, path::Path};
+pub mod detect;
 
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::IsTerminal,
+    path::{Component, Path, PathBuf},
+};
+
+use clap::Args;
 use color_eyre::{
     eyre::{self, WrapErr},
     Help,
 };
 use console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect};
 use std::io::prelude::*;
+use toml_edit::{value, ArrayOfTables, Document, Table};
 use tracing::{debug, info, instrument, warn};
 
-use crate::cli::CliContext;
+use crate::git::backend::{open_backend, BackendKind};
+
+use self::detect::{detect_manifests, suggest_tagprefix, Manifest};
 
 const TEMPLATE: &str = include_str!("project.toml");
 
+/// Everything in the template after this line is about the files, which get replaced by the detected ones
+const FILES_SECTION: &str =
+    "# the files array indicates files inside of the subpath that should be handled by the tool\n";
+
+/// Options of the init mode
+#[derive(Args, Debug, Default, Clone, Copy)]
+pub struct InitOptions {
+    /// Don't ask anything during --init, use every detected manifest and the suggested tagprefix
+    #[arg(short, long, default_value_t = false)]
+    pub yes: bool,
+    /// Also look for manifests in subdirectories during --init, e.g. in a monorepo
+    #[arg(long, default_value_t = false)]
+    pub recursive: bool,
+}
+
+fn to_config_path(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+/// The deepest directory containing all manifests, relative to the scanned directory
+fn common_directory(manifests: &[Manifest]) -> PathBuf {
+    let mut common: Option<Vec<Component>> = None;
+    for manifest in manifests {
+        let parent = manifest.path.parent().unwrap_or(Path::new(""));
+        let components: Vec<Component> = parent.components().collect();
+        common = Some(match common {
+            None => components,
+            Some(common) => common
+                .into_iter()
+                .zip(components)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.unwrap_or_default().iter().collect()
+}
+
+/// Every version of the manifests once, sorted
+fn distinct_versions(manifests: &[Manifest]) -> Vec<&str> {
+    let versions: BTreeSet<&str> = manifests
+        .iter()
+        .map(|manifest| manifest.version.as_str())
+        .collect();
+    versions.into_iter().collect()
+}
+
+/// The files entries of the template as comments, the examples of a config without any detected manifest
+fn commented_files() -> String {
+    let start = TEMPLATE
+        .find(FILES_SECTION)
+        .expect("the template has a files section");
+    TEMPLATE[start + FILES_SECTION.len()..]
+        .lines()
+        .map(|line| {
+            if line.is_empty() || line.starts_with('#') {
+                format!("{}\n", line)
+            } else {
+                format!("# {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// Fills the template with the detected manifests, without any the files stay empty and the entries of the template are only examples
+fn render(manifests: &[Manifest], tagprefix: &str) -> eyre::Result<String> {
+    let header = &TEMPLATE[..TEMPLATE
+        .find(FILES_SECTION)
+        .expect("the template has a files section")];
+    let mut config = header
+        .parse::<Document>()
+        .wrap_err("Failed to parse the template")?;
+    let subpath = common_directory(manifests);
+    config["subpath"] = value(to_config_path(&subpath));
+    config["tagprefix"] = value(tagprefix);
+    if manifests.is_empty() {
+        return Ok(format!(
+            "{}{}# nothing was detected, replace the empty array with entries like the ones below\nfiles = []\n\n{}",
+            config,
+            FILES_SECTION,
+            commented_files()
+        ));
+    }
+
+    let mut files = ArrayOfTables::new();
+    for manifest in manifests {
+        let path = manifest
+            .path
+            .strip_prefix(&subpath)
+            .wrap_err("The manifest is outside of the subpath")?;
+        let mut file = Table::new();
+        file["path"] = value(to_config_path(path));
+        file["key"] = value(&manifest.key);
+        file["type"] = value(manifest.type_name());
+        files.push(file);
+    }
+    // a document of its own, the header ends in comments that would end up below the files otherwise
+    let mut files_section = Document::new();
+    files_section["files"] = toml_edit::Item::ArrayOfTables(files);
+    Ok(format!(
+        "{}{}# path is relative to the subpath, key is where the version is in the file and type tells the tool how to parse it\n{}",
+        config, FILES_SECTION, files_section
+    ))
+}
+
+/// Lets the user pick the manifests and the tagprefix, `None` if they don't want to write the config after all
+fn prompt(
+    manifests: Vec<Manifest>,
+    tagprefix: String,
+    path: &Path,
+) -> eyre::Result<Option<(Vec<Manifest>, String)>> {
+    let theme = ColorfulTheme::default();
+    let mut selected = manifests;
+    if !selected.is_empty() {
+        let items: Vec<String> = selected
+            .iter()
+            .map(|manifest| {
+                format!(
+                    "{} ({} = {})",
+                    manifest.path.display(),
+                    manifest.key,
+                    manifest.version
+                )
+            })
+            .collect();
+        let chosen = MultiSelect::with_theme(&theme)
+            .with_prompt("Which files contain the version to release?")
+            .items(&items)
+            .defaults(&vec![true; items.len()])
+            .interact()?;
+        selected = selected
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| chosen.contains(i))
+            .map(|(_, manifest)| manifest)
+            .collect();
+    }
+    let tagprefix: String = Input::with_theme(&theme)
+        .with_prompt("Tag prefix")
+        .default(tagprefix)
+        .allow_empty(true)
+        .interact_text()?;
+    let confirmed = Confirm::with_theme(&theme)
+        .with_prompt(format!("Write the config to {}?", path.display()))
+        .default(true)
+        .interact()?;
+    Ok(confirmed.then_some((selected, tagprefix)))
+}
+
+/// Writes a config for the manifests found next to `path` to `path`
 #[instrument(level = "trace")]
-pub fn init_project(cli_context: &CliContext) -> eyre::Result<()> {
+pub fn init_project(
+    path: &Path,
+    options: &InitOptions,
+    git_backend: BackendKind,
+    dryrun: bool,
+) -> eyre::Result<()> {
     info!("{} mode called", style("init").bold());
-    let path = &cli_context.path;
-    let path = Path::new(&path);
     info!(
         "Will create a new config file at {}",
         style(path.display()).bold()
@@ -26,21 +199,76 @@ pub fn init_project(cli_context: &CliContext) -> eyre::Result<()> {
             "A file already exists at {}, not doing anything",
             style(path.display()).bold()
         );
-    } else {
-        if cli_context.dryrun {
-            info!("Dry run is active, not creating a config file");
-            debug!(
-                "Would create a config file at {}",
-                style(path.display()).bold()
-            );
-        } else {
-            let mut file = File::create(path)
-                .wrap_err_with(|| format!("Failed to create file at {}", path.display()))
-                .suggestion("Check if the location is actually writeable by the user")?;
-            file.write_all(TEMPLATE.as_bytes())
-                .wrap_err_with(|| format!("Failed to write to file at {}", path.display()))?;
-            info!("Created a config file at {}", style(path.display()).bold());
+        return Ok(());
+    }
+
+    let root = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    info!("Looking for manifests in {}", style(root.display()).bold());
+    let manifests = detect_manifests(root, options.recursive);
+    for manifest in &manifests {
+        info!(
+            "Found {} with version {}",
+            style(manifest.path.display()).bold(),
+            manifest.version
+        );
+    }
+    let tags = open_backend(git_backend, root)
+        .and_then(|git| git.tags())
+        .unwrap_or_else(|err| {
+            debug!("Not suggesting a tagprefix from the git tags: {:?}", err);
+            Vec::new()
+        });
+    let versions: Vec<&str> = manifests
+        .iter()
+        .map(|manifest| manifest.version.as_str())
+        .collect();
+    let tagprefix = suggest_tagprefix(&tags, &versions).unwrap_or_default();
+
+    let interactive = !options.yes && std::io::stdin().is_terminal();
+    if !options.yes && !interactive {
+        warn!("Not running in a terminal, using the detected defaults. Pass --yes to silence this warning");
+    }
+    let (manifests, tagprefix) = if interactive {
+        match prompt(manifests, tagprefix, path)? {
+            Some(answers) => answers,
+            None => {
+                info!("Not creating a config file");
+                return Ok(());
+            }
         }
+    } else {
+        (manifests, tagprefix)
+    };
+
+    if manifests.is_empty() {
+        warn!("No manifest with a version was selected, the files have to be added to the config by hand");
+    }
+    let versions = distinct_versions(&manifests);
+    if versions.len() > 1 {
+        warn!(
+            "The selected files have different versions: {}",
+            versions.join(", ")
+        );
+    }
+    let content = render(&manifests, &tagprefix)?;
+
+    if dryrun {
+        info!("Dry run is active, not creating a config file");
+        debug!(
+            "Would create a config file at {} with the content:\n{}",
+            style(path.display()).bold(),
+            content
+        );
+    } else {
+        let mut file = File::create(path)
+            .wrap_err_with(|| format!("Failed to create file at {}", path.display()))
+            .suggestion("Check if the location is actually writeable by the user")?;
+        file.write_all(content.as_bytes())
+            .wrap_err_with(|| format!("Failed to write to file at {}", path.display()))?;
+        info!("Created a config file at {}", style(path.display()).bold());
     }
     Ok(())
 }
@@ -51,11 +279,90 @@ mod tests {
 
     use crate::config::Config;
 
-    use super::TEMPLATE;
+    use super::*;
 
     #[test]
     fn template_should_be_parseable() {
         let config = TEMPLATE.parse::<Document>().unwrap();
         _ = from_document::<Config>(config).unwrap();
     }
+
+    #[test]
+    fn template_should_match_the_schema() {
+        let schema = serde_json::to_value(Config::schema()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let template: serde_json::Value = toml_edit::de::from_str(TEMPLATE).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&template)
+            .map(|error| error.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let invalid = serde_json::json!({ "subpath": ".", "tagprefix": "", "files": [{ "path": "x", "key": "version", "type": "yaml" }] });
+        assert!(!validator.is_valid(&invalid));
+    }
+
+    #[test]
+    fn versions_are_reported_once() {
+        let manifest = |version: &str| Manifest {
+            path: PathBuf::from("package.json"),
+            key: "version".to_string(),
+            project_type: crate::config::ProjectType::Json,
+            version: version.to_string(),
+        };
+        let manifests = [manifest("1.0.0"), manifest("2.0.0"), manifest("1.0.0")];
+        assert_eq!(distinct_versions(&manifests), vec!["1.0.0", "2.0.0"]);
+        assert_eq!(distinct_versions(&manifests[..1]), vec!["1.0.0"]);
+    }
+
+    #[test]
+    fn rendered_config_passes_the_check() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("packages/app")).unwrap();
+        std::fs::write(
+            root.join("packages/app/package.json"),
+            r#"{"version": "1.2.0"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("packages/Cargo.toml"),
+            "[package]\nversion = \"1.2.0\"\n",
+        )
+        .unwrap();
+        let manifests = detect::detect_manifests(root, true);
+        let content = render(&manifests, "v").unwrap();
+        assert!(content.contains("subpath = \"packages\""), "{}", content);
+        assert!(
+            content.contains("path = \"app/package.json\""),
+            "{}",
+            content
+        );
+        assert!(
+            content.contains("# leave this empty to have no prefix at all here\ntagprefix = \"v\"")
+        );
+
+        let path = root.join("project.toml");
+        std::fs::write(&path, content).unwrap();
+        let result = crate::config::check::check(
+            &crate::config::source::ConfigSource::from_path(&path).unwrap(),
+            &Default::default(),
+        );
+        assert!(result.problems.is_empty(), "{}", result);
+    }
+
+    #[test]
+    fn nothing_detected_renders_no_files() {
+        let content = render(&[], "app-v").unwrap();
+        let config: Config = from_document(content.parse::<Document>().unwrap()).unwrap();
+        assert!(config.files.is_empty());
+        assert_eq!(config.subpath, ".");
+        assert_eq!(config.tagprefix, "app-v");
+        // the entries of the template are still there as examples
+        assert!(
+            content.contains("# path = \"package.json\"\n"),
+            "{}",
+            content
+        );
+        assert!(content.contains("# [[files]]\n"), "{}", content);
+    }
 }
@@ -0,0 +1,61 @@
+//! Automated version handling for repos & monorepos, driven by conventional commit messages.
+//!
+//! The `semantic-release` binary is a thin CLI over this library. A release is done in two steps,
+//! [Releaser::plan] computes a [ReleasePlan] from the commits since the last tag without touching anything
+//! and [Releaser::apply] writes, commits and tags it:
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use semantic_release::{config::source, ReleaseOptions, Releaser};
+//!
+//! # fn main() -> color_eyre::eyre::Result<()> {
+//! let (config, source) = source::load(None, &Default::default())?;
+//! let releaser = Releaser::new(config, &source.directory()?, ReleaseOptions::default())?;
+//! if let Some(plan) = releaser.plan()? {
+//!     println!("Releasing {}", plan.next_version);
+//!     releaser.apply(&plan)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Semver guarantees
+//!
+//! The library follows [Semantic Versioning](https://semver.org/) together with the binary:
+//!
+//! - The items re-exported at the crate root ([Config], [SemanticVersion], [BumpLevel], [VersionFile],
+//!   [ReleasePlan], [Releaser], [ReleaseOptions]) and the public modules only change incompatibly in a major release.
+//! - Public struct fields may be added in a minor release, build the structs with `..Default::default()`
+//!   where they implement [Default] and don't match on them exhaustively.
+//! - New variants may be added to [BumpLevel] and [config::ProjectType] in a minor release.
+//! - Log output and the wording of error messages are not part of the API.
+//! - Items hidden from the documentation, e.g. `init`, exist for the CLI and may change at any time.
+
+pub mod channel;
+mod command;
+pub mod config;
+pub mod consistency;
+pub mod git;
+pub mod hooks;
+#[doc(hidden)]
+pub mod init;
+pub mod lint;
+pub mod notes;
+pub mod preflight;
+pub mod project;
+pub mod release;
+pub mod report;
+pub mod scheme;
+pub mod semver;
+#[cfg(test)]
+mod testing;
+pub mod transaction;
+
+pub use crate::{
+    config::Config,
+    git::BumpLevel,
+    project::VersionFile,
+    release::{ReleaseOptions, ReleasePlan, Releaser},
+    semver::SemanticVersion,
+};
@@ -0,0 +1,528 @@
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use console::style;
+use tracing::{info, instrument, warn};
+
+use crate::{
+    config::{source, Commits},
+    git::{backend::GitBackend, RELEASE_COMMIT_PREFIX},
+};
+
+/// First line of the commit-msg hooks written by [install_hook]
+const HOOK_MARKER: &str = "# installed by semantic-release lint --install-hook";
+
+/// git puts everything below this line into the message file, but drops it from the commit
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// A single thing wrong with a commit message
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub message: String,
+    /// 1-based line of the message
+    pub line: usize,
+    /// Character range in the line the problem is about
+    pub columns: Range<usize>,
+}
+
+impl Problem {
+    fn new(message: String, line: usize, columns: Range<usize>) -> Problem {
+        Problem {
+            message,
+            line,
+            columns,
+        }
+    }
+}
+
+/// Every problem of one commit message, together with the message to point at them
+pub struct LintResult {
+    /// Where the message came from, a file, stdin or a commit id
+    pub source: String,
+    pub message: String,
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for LintResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for problem in &self.problems {
+            let column = problem.columns.start + 1;
+            writeln!(
+                f,
+                "{}:{}:{}: {}",
+                self.source, problem.line, column, problem.message
+            )?;
+            let line = self
+                .message
+                .lines()
+                .nth(problem.line - 1)
+                .unwrap_or_default();
+            let width = problem.columns.len().max(1);
+            writeln!(f, "  {}", line)?;
+            writeln!(f, "  {}{}", " ".repeat(column - 1), "^".repeat(width))?;
+        }
+        Ok(())
+    }
+}
+
+/// The message like git commits it, without the comments for the editor and anything below the scissors line
+pub fn clean(message: &str) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .take_while(|line| *line != SCISSORS)
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Messages git and semantic-release write themselves
+fn generated(summary: &str) -> bool {
+    [
+        "Merge ",
+        "Revert \"",
+        "fixup! ",
+        "squash! ",
+        "amend! ",
+        RELEASE_COMMIT_PREFIX,
+    ]
+    .iter()
+    .any(|prefix| summary.starts_with(prefix))
+}
+
+/// Number of single character edits to get from `a` to `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// `, did you mean `x`?` for the closest allowed value, if there is a close one
+fn did_you_mean(value: &str, allowed: &[String]) -> String {
+    allowed
+        .iter()
+        .map(|candidate| (distance(value, candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, candidate)| format!(", did you mean `{}`?", candidate))
+        .unwrap_or_default()
+}
+
+/// Checks a cleaned message against the commit template and the allowed types and scopes
+pub fn lint(message: &str, commits: &Commits) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut lines = message.lines();
+    let summary = lines.next().unwrap_or_default();
+    if summary.trim().is_empty() {
+        problems.push(Problem::new("the message is empty".to_string(), 1, 0..0));
+        return problems;
+    }
+    if generated(summary) {
+        return problems;
+    }
+    // columns are counted in characters, the offsets below are bytes
+    let column = |offset: usize| summary[..offset].chars().count();
+    let span = |start: usize, end: usize| column(start)..column(end);
+    let at = |offset: usize| {
+        let column = column(offset.min(summary.len()));
+        column..column + 1
+    };
+
+    let type_end = summary
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(summary.len());
+    let kind = &summary[..type_end];
+    if kind.is_empty() {
+        problems.push(Problem::new(
+            "missing type at the start of the summary".to_string(),
+            1,
+            0..1,
+        ));
+    } else if !commits.types.contains(&kind.to_string()) {
+        problems.push(Problem::new(
+            format!(
+                "unknown type `{}`{}",
+                kind,
+                did_you_mean(kind, &commits.types)
+            ),
+            1,
+            span(0, type_end),
+        ));
+    }
+
+    let mut position = type_end;
+    let mut before_colon = "type";
+    if summary[position..].starts_with('(') {
+        let Some(close) = summary[position..].find(')') else {
+            problems.push(Problem::new(
+                "missing `)` after the scope".to_string(),
+                1,
+                span(position, summary.len()),
+            ));
+            return problems;
+        };
+        let scope = &summary[position + 1..position + close];
+        if scope.trim().is_empty() {
+            problems.push(Problem::new(
+                "empty scope, leave out the parentheses for no scope".to_string(),
+                1,
+                span(position, position + close + 1),
+            ));
+        } else if !commits.scopes.is_empty() && !commits.scopes.contains(&scope.to_string()) {
+            problems.push(Problem::new(
+                format!(
+                    "unknown scope `{}`{}",
+                    scope,
+                    did_you_mean(scope, &commits.scopes)
+                ),
+                1,
+                span(position + 1, position + close),
+            ));
+        }
+        position += close + 1;
+        before_colon = "scope";
+    }
+    if summary[position..].starts_with('!') {
+        problems.push(Problem::new(
+            "`!` doesn't mark a breaking change, put `BREAKING CHANGE:` into the body instead"
+                .to_string(),
+            1,
+            at(position),
+        ));
+        position += 1;
+    }
+    if !summary[position..].starts_with(':') {
+        problems.push(Problem::new(
+            format!("missing colon after the {}", before_colon),
+            1,
+            at(position),
+        ));
+        return problems;
+    }
+    position += 1;
+    let description = &summary[position..];
+    if description.trim().is_empty() {
+        problems.push(Problem::new(
+            "missing summary after the colon".to_string(),
+            1,
+            at(position - 1),
+        ));
+    } else if !description.starts_with(' ') {
+        problems.push(Problem::new(
+            "missing space after the colon".to_string(),
+            1,
+            at(position - 1),
+        ));
+    }
+
+    if let Some(second) = lines.next() {
+        if !second.is_empty() {
+            problems.push(Problem::new(
+                "missing blank line between the summary and the body".to_string(),
+                2,
+                0..second.chars().count(),
+            ));
+        }
+    }
+    problems
+}
+
+/// The allowed types and scopes of the config, the defaults if no config can be found
+#[instrument(level = "trace", skip(overrides))]
+pub fn settings(
+    config: Option<&str>,
+    overrides: &source::Overrides,
+) -> eyre::Result<(Commits, PathBuf)> {
+    if config.is_none() {
+        if let Err(err) = source::locate(None) {
+            warn!(
+                "Linting with the default commit types, no config found: {}",
+                err
+            );
+            return Ok((Commits::default(), PathBuf::from(".")));
+        }
+    }
+    let (config, source) = source::load(config, overrides)?;
+    let directory = source.directory()?.join(&config.subpath);
+    Ok((config.commits, directory))
+}
+
+fn report(results: &[LintResult], commits: &Commits) -> eyre::Result<()> {
+    let problems: usize = results.iter().map(|result| result.problems.len()).sum();
+    if problems == 0 {
+        info!("No problems found");
+        return Ok(());
+    }
+    for result in results {
+        eprint!("{}", result);
+    }
+    Err(eyre::eyre!(
+        "Found {} problem(s) in the commit message(s)",
+        problems
+    ))
+    .suggestion(format!(
+        "Summaries look like `<type>(<scope>): <summary>`, the allowed types are {}",
+        commits.types.join(", ")
+    ))
+}
+
+/// Lints the message in `path`, stdin for `-` or no path.
+/// A file is cleaned up like git does before it commits the message, stdin is linted as it is.
+#[instrument(level = "trace", skip(commits))]
+pub fn lint_file(path: Option<&Path>, commits: &Commits) -> eyre::Result<()> {
+    let (source, message) = match path {
+        Some(path) if path != Path::new("-") => {
+            let message = fs::read_to_string(path)
+                .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+            (path.display().to_string(), clean(&message))
+        }
+        _ => {
+            let mut message = String::new();
+            io::stdin()
+                .read_to_string(&mut message)
+                .wrap_err("Failed to read the message from stdin")?;
+            ("stdin".to_string(), message.trim_end().to_string())
+        }
+    };
+    let problems = lint(&message, commits);
+    report(
+        &[LintResult {
+            source,
+            message,
+            problems,
+        }],
+        commits,
+    )
+}
+
+/// Lints every commit in `range`, `<since>..<until>` or a single revision for all of its history.
+/// Lines starting with `#` are part of committed messages, they are linted too.
+#[instrument(level = "trace", skip(git, commits))]
+pub fn lint_range(git: &dyn GitBackend, range: &str, commits: &Commits) -> eyre::Result<()> {
+    let (since, until) = match range.split_once("..") {
+        Some((since, until)) => (
+            Some(since).filter(|since| !since.is_empty()),
+            Some(until)
+                .filter(|until| !until.is_empty())
+                .unwrap_or("HEAD"),
+        ),
+        None => (None, range),
+    };
+    let found = git
+        .commits(since, until, Path::new("."))
+        .wrap_err_with(|| format!("Failed to get the commits of {}", range))?;
+    info!(
+        "Linting {} commit(s) of {}",
+        found.len(),
+        style(range).bold()
+    );
+    let results: Vec<LintResult> = found
+        .into_iter()
+        .map(|commit| {
+            let message = commit.message.trim_end().to_string();
+            LintResult {
+                source: commit.id.chars().take(7).collect(),
+                problems: lint(&message, commits),
+                message,
+            }
+        })
+        .collect();
+    report(&results, commits)
+}
+
+/// `value` quoted for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Writes a commit-msg hook calling `semantic-release lint`, returns where it was written.
+/// `config` is passed on to the hook, it has to be absolute or relative to the root of the worktree.
+#[instrument(level = "trace", skip(git))]
+pub fn install_hook(git: &dyn GitBackend, config: Option<&str>) -> eyre::Result<PathBuf> {
+    let directory = git.hooks_directory()?;
+    let path = directory.join("commit-msg");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !existing.contains(HOOK_MARKER) {
+            return Err(eyre::eyre!("{} exists already", path.display())).suggestion(
+                "Call `semantic-release lint \"$1\"` from the existing hook or remove it",
+            );
+        }
+    }
+    let executable = std::env::current_exe().wrap_err("Failed to find the own executable")?;
+    let config = config
+        .map(|config| format!(" --config {}", shell_quote(config)))
+        .unwrap_or_default();
+    let hook = format!(
+        "#!/bin/sh\n{}\nexec {} lint{} \"$1\"\n",
+        HOOK_MARKER,
+        shell_quote(&executable.display().to_string()),
+        config
+    );
+    fs::create_dir_all(&directory)
+        .wrap_err_with(|| format!("Failed to create {}", directory.display()))?;
+    fs::write(&path, hook).wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .wrap_err_with(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        git::backend::{open_backend, BackendKind},
+        testing,
+    };
+
+    use super::*;
+
+    fn problems(message: &str) -> Vec<String> {
+        let commits = Commits {
+            scopes: vec!["cli".to_string(), "config".to_string()],
+            ..Default::default()
+        };
+        lint(&clean(message), &commits)
+            .into_iter()
+            .map(|problem| {
+                format!(
+                    "{}:{}: {}",
+                    problem.line,
+                    problem.columns.start + 1,
+                    problem.message
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_messages() {
+        assert!(problems("feat: a feature").is_empty());
+        assert!(problems("fix(cli): a fix\n\nwith a body\n\nBREAKING CHANGE: gone").is_empty());
+        assert!(problems("Merge branch 'main' into feature").is_empty());
+        assert!(problems("[Semantic release]: Release 1.2.0").is_empty());
+        // what git adds for the editor doesn't count
+        assert!(problems(
+            "docs: readme\n# Please enter the commit message\n# ------------------------ >8 ------------------------\ndiff"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn precise_problems() {
+        assert_eq!(
+            problems("feet: a feature"),
+            vec!["1:1: unknown type `feet`, did you mean `feat`?"]
+        );
+        assert_eq!(
+            problems("feat a feature"),
+            vec!["1:5: missing colon after the type"]
+        );
+        assert_eq!(problems("fix"), vec!["1:4: missing colon after the type"]);
+        assert_eq!(
+            problems("fix(clli): a fix"),
+            vec!["1:5: unknown scope `clli`, did you mean `cli`?"]
+        );
+        assert_eq!(
+            problems("fix(cli a fix"),
+            vec!["1:4: missing `)` after the scope"]
+        );
+        assert_eq!(
+            problems("feat!:a feature\nbody"),
+            vec![
+                "1:5: `!` doesn't mark a breaking change, put `BREAKING CHANGE:` into the body instead",
+                "1:6: missing space after the colon",
+                "2:1: missing blank line between the summary and the body",
+            ]
+        );
+        assert_eq!(
+            problems("# only comments"),
+            vec!["1:1: the message is empty"]
+        );
+    }
+
+    #[test]
+    fn rendered_problems() {
+        let message = "feet: a feature".to_string();
+        let result = LintResult {
+            source: "COMMIT_EDITMSG".to_string(),
+            problems: lint(&message, &Commits::default()),
+            message,
+        };
+        assert_eq!(
+            result.to_string(),
+            "COMMIT_EDITMSG:1:1: unknown type `feet`, did you mean `feat`?\n  feet: a feature\n  ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn committed_comment_lines_are_linted() {
+        let dir = testing::repo();
+        let path = dir.path();
+        fs::write(path.join("file"), "content").unwrap();
+        testing::git(path, &["add", "file"]);
+        testing::git(
+            path,
+            &[
+                "commit",
+                "--quiet",
+                "--cleanup=verbatim",
+                "-m",
+                "fix: a fix\n#12 is fixed",
+            ],
+        );
+        let git = open_backend(BackendKind::Gitoxide, path).unwrap();
+        assert!(lint_range(git.as_ref(), "HEAD", &Commits::default()).is_err());
+    }
+
+    #[test]
+    fn hook_installation() {
+        let dir = testing::repo();
+        let git = open_backend(BackendKind::Gitoxide, dir.path()).unwrap();
+
+        let hook = install_hook(git.as_ref(), Some("/repo/project.toml")).unwrap();
+        let content = fs::read_to_string(&hook).unwrap();
+        assert!(content.starts_with(&format!("#!/bin/sh\n{}\n", HOOK_MARKER)));
+        assert!(content.ends_with(" lint --config '/repo/project.toml' \"$1\"\n"));
+        // installing again replaces the own hook
+        install_hook(git.as_ref(), Some("/repo/it's/project.toml")).unwrap();
+        let content = fs::read_to_string(&hook).unwrap();
+        assert!(content.ends_with(" lint --config '/repo/it'\\''s/project.toml' \"$1\"\n"));
+        #[cfg(unix)]
+        {
+            // the shell has to see the path as it was given
+            let quoted = shell_quote("/repo/it's/project.toml");
+            let output = std::process::Command::new("sh")
+                .args(["-c", &format!("printf %s {}", quoted)])
+                .output()
+                .unwrap();
+            assert_eq!(output.stdout, b"/repo/it's/project.toml");
+        }
+
+        fs::write(&hook, "#!/bin/sh\nrun-other-checks\n").unwrap();
+        let err = install_hook(git.as_ref(), None).unwrap_err();
+        assert!(err.to_string().ends_with("commit-msg exists already"));
+        assert_eq!(
+            fs::read_to_string(&hook).unwrap(),
+            "#!/bin/sh\nrun-other-checks\n"
+        );
+    }
+}
@@ -1,35 +1,22 @@
-use std::fs;
-use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::{fs, path::Path};
 
 use color_eyre::eyre::{self, WrapErr};
 use console::style;
-use tempfile::NamedTempFile;
-use tracing::{debug, info, span, warn, Level};
+use tracing::info;
 
-use crate::git::BumpLevel;
-use crate::project::load_versionfile;
-use crate::{cli::CliContext, git::calc_bumplevel, semver::SemanticVersion};
+use semantic_release::{
+    config,
+    git::{self, backend::open_backend},
+    init, lint,
+    notes::{self, ReleaseNotes},
+    scheme::CalendarVersion,
+    semver::SemanticVersion,
+    transaction, ReleaseOptions, ReleasePlan, Releaser,
+};
 
-mod cli;
-mod config;
-mod git;
-mod init;
-mod project;
-mod semver;
+use crate::cli::{CliContext, Command};
 
-fn run_command(executable: &str, cwd: &PathBuf, args: Vec<String>) -> eyre::Result<Output> {
-    let mut command = Command::new(executable);
-    command.args(args).current_dir(cwd);
-    let program = command.get_program().to_str().unwrap();
-    let args = command.get_args();
-    debug!("Executing {:?} {:?}", program, args);
-    let output = command
-        .output()
-        .context("Failed to get output from command")?;
-    Ok(output)
-}
+mod cli;
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
@@ -39,166 +26,182 @@ fn main() -> eyre::Result<()> {
         .without_time()
         .with_max_level(cli_context.log_level)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 
-    if cli_context.init {
-        init::init_project(&cli_context).wrap_err("Failed to initialize a new config file")?;
-    } else {
-        let config = config::Config::from_path(&cli_context.path)
-            .context("Failed to build configuration")?;
-
-        let path = Path::new(&cli_context.path);
-        let path = path.parent().unwrap();
-        let path = fs::canonicalize(path)?;
-        let subpath = path.join(&config.subpath);
-
-        let mut semver = SemanticVersion::new();
-        let mut commit_changes = false;
-
-        config.files.iter().try_for_each(|file| -> eyre::Result<()> {
-        let span = span!(Level::TRACE, "file", file = &file.path);
-        let _guard = span.enter();
-        let filename = &file.path;
-        info!("Handling file {}", style(&filename).bold());
-        let filepath = subpath.join(filename);
-        let project_type = &file.project_type;
-
-        debug!("Path: {}", &filepath.display());
-        let mut version_file = load_versionfile(&filepath, file).context("Failed to build internal representation of project file")?;
-
-        let version = version_file.read_version()
-            .context("Failed to read version from project file")?;
-
-        debug!("Version: {}", version);
-
-        info!("Fetching tags");
-        let args = vec![
-            "tag".to_owned(),
-            "--list".to_owned(),
-            format!("{}{}", &config.tagprefix, &version),
-            "--sort=-creatordate".to_owned(),
-        ];
-        let tags = run_command("git", &subpath, args).context("Failed to get git tags")?;
-
-        let tags: Vec<&str> = std::str::from_utf8(&tags.stdout)?
-            .split('\n')
-            .filter(|line| !line.is_empty())
-            .collect();
-        if tags.is_empty() {
-            warn!("Could not find a tag matching {}", &config.tagprefix);
-            warn!("Stopping execution");
-        } else {
-            debug!("matching tags: {:?}", tags);
-            let last_tag = *tags.first().unwrap();
-            info!(
-                "Found {} as the latest relevant tag",
-                style(last_tag).bold()
-            );
-
-            info!("Fetching relevant commits");
-            let args = vec![
-                "rev-list".to_owned(),
-                format!("{}..HEAD", last_tag),
-                "--format=%B".to_owned(),
-                ".".to_owned(),
-            ];
-            let commits = run_command("git", &subpath, args).context("Failed to get git commits")?;
-            let commits: Vec<&str> = std::str::from_utf8(&commits.stdout)?
-                .split('\n')
-                .filter(|line| !line.is_empty())
-                .collect();
-            debug!("Found {:?} as relevant commits", commits);
-
-            if commits.is_empty() {
-                info!("No relevant commits found. Not doing anything");
-                return Ok(());
-            }
-
-            info!("Calculating Bumplevel");
-            let bumplevel = calc_bumplevel(&commits);
-            info!("Bumplevel: {:?}", style(&bumplevel).bold());
-
-            if bumplevel == BumpLevel::None {
-                commit_changes = false;
-            }
-
-            semver.set_version(&version)
-                .context("Failed to parse version into a semantic version")?;
-            semver.bump(bumplevel);
-
-            info!("Parsing {:?} with type {:?}", filepath, project_type);
-            let json =
-                version_file.update_project(&semver).context("Failed to update file JSON")?;
-            if cli_context.dryrun {
-                info!("Dry run is active, not writing the file");
-                debug!("Would write {}", json);
+    if let Some(Command::Check { .. }) = &cli_context.command {
+        config::check::check_config(cli_context.config.as_deref(), &cli_context.overrides)?;
+    } else if let Some(Command::Config { .. }) = &cli_context.command {
+        let shown = config::source::show(cli_context.config.as_deref(), &cli_context.overrides)
+            .wrap_err("Failed to show the config")?;
+        print!("{}", shown);
+    } else if let Some(Command::Schema) = &cli_context.command {
+        let schema = serde_json::to_string_pretty(&config::Config::schema())
+            .wrap_err("Failed to serialize the schema")?;
+        println!("{}", schema);
+    } else if cli_context.init {
+        let path = Path::new(cli_context.config.as_deref().unwrap_or("project.toml"));
+        init::init_project(
+            path,
+            &cli_context.init_options,
+            cli_context.git_backend,
+            cli_context.dryrun,
+        )
+        .wrap_err("Failed to initialize a new config file")?;
+    } else if let Some(Command::Lint {
+        file,
+        range,
+        install_hook,
+        ..
+    }) = &cli_context.command
+    {
+        let (commits, directory) =
+            lint::settings(cli_context.config.as_deref(), &cli_context.overrides)?;
+        if *install_hook || range.is_some() {
+            let git = open_backend(cli_context.git_backend, &directory)
+                .context("Failed to open the git repository")?;
+            if let Some(range) = range {
+                lint::lint_range(git.as_ref(), range, &commits)?;
             } else {
-                let mut file_handle = NamedTempFile::new().context("Failed to create temporary file")?;
-                file_handle.write_all(json.as_bytes()).context("Failed to write to temporary file, maybe the user is lacking the necessary permission")?;
-                let path = &file_handle.path().clone();
-                info!("Successfully updated the project file");
-                debug!("Moving temporay file {:?} to {:?}", &path, &filepath);
-                fs::copy(path, &filepath).context("Failed to copy from temporary file to target")?;
-            }
-            info!(
-                "Adding {} to the git commit",
-                style(&filepath.display()).bold()
-            );
-            // TODO: There must be a better way than format!
-            let args = vec!["add".to_owned(), format!("{}", filename)];
-            if cli_context.dryrun {
-                info!("Dry run is active, not adding the file");
-                debug!(
-                    "Would run git with the arguments {:?} from the directory {:?}",
-                    args, &subpath
+                let config = cli_context
+                    .config
+                    .as_deref()
+                    .map(|config| fs::canonicalize(config).map(|path| path.display().to_string()))
+                    .transpose()?;
+                let hook = lint::install_hook(git.as_ref(), config.as_deref())?;
+                info!(
+                    "Installed the commit-msg hook at {}",
+                    style(hook.display()).bold()
                 );
-            } else {
-                run_command("git", &subpath, args).context("Failed to execute git add")?;
             }
-            commit_changes = true;
+        } else {
+            lint::lint_file(file.as_deref(), &commits)?;
         }
-
-        eyre::Result::Ok(())
-    })?;
-
-        if commit_changes {
-            info!("Doing the git commit");
-            let args = vec![
-                "commit".to_string(),
-                "-m".to_string(),
-                format!("[Semantic release]: Release {}", &semver.to_string()),
-            ];
-            if cli_context.dryrun {
-                info!("Dry run is active, not commiting anything");
-                debug!(
-                    "Would run git with the arguments {:?} from the directory {:?}",
-                    args, &subpath
-                );
-            } else {
-                run_command("git", &subpath, args).context("Failed to execute git commit")?;
-            }
-
-            // TODO: Maybe make tagging optional?
-            let tag = format!("{}{}", &config.tagprefix, &semver.to_string());
-            info!("Tagging the release with tag {}", style(&tag).bold());
-            let args = vec!["tag".to_string(), tag];
-            if cli_context.dryrun {
-                info!("Dry run is active, not tagging anything");
-                debug!(
-                    "Would run git with the arguments {:?} from the directory {:?}",
-                    args, &subpath
+    } else if let Some(Command::Notes { range, format, .. }) = &cli_context.command {
+        let (config, source) =
+            config::source::load(cli_context.config.as_deref(), &cli_context.overrides)
+                .context("Failed to build configuration")?;
+        let git = open_backend(
+            cli_context.git_backend,
+            &source.directory()?.join(&config.subpath),
+        )
+        .context("Failed to open the git repository")?;
+        let tags = git.tags().context("Failed to get git tags")?;
+        let range = match config.scheme.calendar_format()? {
+            None => notes::resolve_range(range, &tags, &config.tagprefix, |raw| {
+                raw.parse::<SemanticVersion>()
+            }),
+            Some(format) => notes::resolve_range(range, &tags, &config.tagprefix, |raw| {
+                CalendarVersion::parse(raw, &format)
+            }),
+        }?;
+        let commits = git
+            .commits(range.since.as_deref(), &range.until, Path::new("."))
+            .context("Failed to get git commits")?;
+        let notes = ReleaseNotes::new(
+            &range.title,
+            &git::drop_reverted(commits),
+            &config.revert_type,
+            &config.references,
+        );
+        print!("{}", notes.render(*format)?);
+    } else if let Some(Command::Changelog { rebuild, file, .. }) = &cli_context.command {
+        let (config, source) =
+            config::source::load(cli_context.config.as_deref(), &cli_context.overrides)
+                .context("Failed to build configuration")?;
+        let subpath = source.directory()?.join(&config.subpath);
+        let git = open_backend(cli_context.git_backend, &subpath)
+            .context("Failed to open the git repository")?;
+        let changelog = match config.scheme.calendar_format()? {
+            None => notes::changelog(
+                git.as_ref(),
+                &config.tagprefix,
+                &config.revert_type,
+                &config.references,
+                |raw| raw.parse::<SemanticVersion>(),
+            ),
+            Some(format) => notes::changelog(
+                git.as_ref(),
+                &config.tagprefix,
+                &config.revert_type,
+                &config.references,
+                |raw| CalendarVersion::parse(raw, &format),
+            ),
+        }?;
+        if *rebuild {
+            let path = match (file, &config.changelog) {
+                (Some(file), _) => subpath.join(file),
+                (None, Some(changelog)) => subpath.join(changelog),
+                (None, None) => subpath.join("CHANGELOG.md"),
+            };
+            transaction::write(&path, changelog.as_bytes())
+                .wrap_err_with(|| format!("Failed to write the changelog to {}", path.display()))?;
+            info!("Rebuilt {}", style(path.display()).bold());
+        } else {
+            print!("{}", changelog);
+        }
+    } else if let Some(Command::Plan { out, .. }) = &cli_context.command {
+        let releaser = releaser(&cli_context)?;
+        let Some(plan) = plan(&releaser, &cli_context)? else {
+            info!("Nothing to change");
+            return Ok(());
+        };
+        match out {
+            Some(out) => {
+                plan.write(out)?;
+                info!(
+                    "Wrote the plan for {} to {}, release it with {}",
+                    style(&plan.next_version).bold(),
+                    style(out.display()).bold(),
+                    style(format!("semantic-release apply {}", out.display())).bold()
                 );
-            } else {
-                run_command("git", &subpath, args).context("Failed to execute git tag")?;
             }
-
-            info!(
-                "All done! keep in mind that this doesn't do a {}",
-                style("git push").bold()
-            );
-        } else {
+            None => println!("{}", plan.to_json()?),
+        }
+    } else if let Some(Command::Apply { plan, .. }) = &cli_context.command {
+        let plan = ReleasePlan::read(plan)?;
+        releaser(&cli_context)?.apply(&plan)?;
+        done();
+    } else {
+        let releaser = releaser(&cli_context)?;
+        let Some(plan) = plan(&releaser, &cli_context)? else {
             info!("Nothing to change");
+            return Ok(());
+        };
+        if cli_context.dryrun {
+            let report = releaser.dry_run(&plan)?;
+            print!("{}", report.render(cli_context.diff_format)?);
+            return Ok(());
         }
+        releaser.apply(&plan)?;
+        done();
     }
     Ok(())
 }
+
+fn releaser(cli_context: &CliContext) -> eyre::Result<Releaser> {
+    let (config, source) =
+        config::source::load(cli_context.config.as_deref(), &cli_context.overrides)
+            .context("Failed to build configuration")?;
+    let options = ReleaseOptions {
+        git_backend: cli_context.git_backend,
+        allow: cli_context.allow,
+        version_override: cli_context.version_override.clone(),
+        ..ReleaseOptions::default()
+    };
+    Releaser::new(config, &source.directory()?, options)
+}
+
+fn plan(releaser: &Releaser, cli_context: &CliContext) -> eyre::Result<Option<ReleasePlan>> {
+    match cli_context.sync {
+        Some(target) => releaser.plan_sync(target),
+        None => releaser.plan(),
+    }
+}
+
+fn done() {
+    info!(
+        "All done! keep in mind that this doesn't do a {}",
+        style("git push").bold()
+    );
+}
@@ -0,0 +1,497 @@
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use serde::Serialize;
+
+use std::path::Path;
+
+use tracing::{info, instrument};
+
+use crate::{
+    config::ReferenceLink,
+    git::{backend::GitBackend, commit_to_bumplevel, drop_reverted, BumpLevel, Commit},
+    scheme::Version,
+};
+
+/// How the release notes get printed
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum NotesFormat {
+    /// Markdown with a heading per section, e.g. for chat or a release page
+    #[default]
+    Markdown,
+    /// Plain text without any markup
+    Text,
+    /// A single JSON document
+    Json,
+}
+
+/// One commit in the release notes
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Abbreviated commit id
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    pub description: String,
+    /// The author followed by the co-authors
+    pub authors: Vec<String>,
+    /// Issues and pull requests the commit refers to
+    pub references: Vec<Reference>,
+}
+
+/// A reference to an issue or pull request like `#123` or `PROJ-456`
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Reference {
+    pub id: String,
+    /// From the first entry of the `references` config with a matching prefix
+    pub url: Option<String>,
+}
+
+impl Reference {
+    fn new(id: &str, links: &[ReferenceLink]) -> Reference {
+        let url = links
+            .iter()
+            .find(|link| id.starts_with(&link.prefix))
+            .map(|link| link.url.replace("{id}", id.trim_start_matches('#')));
+        Reference {
+            id: id.to_string(),
+            url,
+        }
+    }
+
+    fn markdown(&self) -> String {
+        match &self.url {
+            Some(url) => format!("[{}]({})", self.id, url),
+            None => self.id.clone(),
+        }
+    }
+}
+
+impl Entry {
+    /// The commit id followed by the references, `render` turns every reference into text
+    fn suffix(&self, render: impl Fn(&Reference) -> String) -> String {
+        let mut parts = vec![self.id.clone()];
+        parts.extend(self.references.iter().map(render));
+        parts.join(", ")
+    }
+}
+
+/// Commits grouped by the bump they lead to
+#[derive(Serialize, Debug)]
+pub struct Section {
+    pub title: &'static str,
+    pub entries: Vec<Entry>,
+}
+
+/// The commits of a release, grouped like they count towards the version
+#[derive(Serialize, Debug)]
+pub struct ReleaseNotes {
+    /// The version or `Unreleased`
+    pub title: String,
+    /// Only sections with entries are part of the notes
+    pub sections: Vec<Section>,
+    /// Everyone who authored or co-authored a commit, in order of their first commit
+    pub contributors: Vec<String>,
+}
+
+/// The sections, highest bump first
+const SECTIONS: [(BumpLevel, &str); 4] = [
+    (BumpLevel::Major, "Breaking changes"),
+    (BumpLevel::Minor, "Features"),
+    (BumpLevel::Patch, "Bug fixes"),
+    (BumpLevel::None, "Other changes"),
+];
+
+impl ReleaseNotes {
+    /// Notes of `commits`, newest first like the git backends return them. The release commits are left out.
+    /// Call [crate::git::drop_reverted] first to leave out reverted commits as well.
+    /// References to issues get a url from the first of `links` with a matching prefix.
+    pub fn new(
+        title: &str,
+        commits: &[Commit],
+        revert_type: &str,
+        links: &[ReferenceLink],
+    ) -> ReleaseNotes {
+        let mut sections: Vec<Section> = SECTIONS
+            .iter()
+            .map(|(_, title)| Section {
+                title,
+                entries: Vec::new(),
+            })
+            .collect();
+        let mut contributors: Vec<String> = Vec::new();
+        // the notes read oldest first
+        for commit in commits.iter().rev().filter(|commit| !commit.is_release()) {
+            let level = commit_to_bumplevel(commit, revert_type);
+            let index = SECTIONS
+                .iter()
+                .position(|(section, _)| *section == level)
+                .expect("every bumplevel has a section");
+            let parts = commit.conventional();
+            let mut authors = vec![commit.author.clone()];
+            authors.extend(commit.co_authors().into_iter().map(str::to_string));
+            authors.retain(|author| !author.is_empty());
+            for author in &authors {
+                if !contributors.contains(author) {
+                    contributors.push(author.clone());
+                }
+            }
+            sections[index].entries.push(Entry {
+                id: commit.id.chars().take(7).collect(),
+                kind: parts.kind.map(str::to_string),
+                scope: parts.scope.map(str::to_string),
+                description: parts.description.to_string(),
+                authors,
+                references: commit
+                    .references()
+                    .into_iter()
+                    .map(|id| Reference::new(id, links))
+                    .collect(),
+            });
+        }
+        sections.retain(|section| !section.entries.is_empty());
+        ReleaseNotes {
+            title: title.to_string(),
+            sections,
+            contributors,
+        }
+    }
+
+    pub fn render(&self, format: NotesFormat) -> eyre::Result<String> {
+        match format {
+            NotesFormat::Markdown => Ok(self.markdown()),
+            NotesFormat::Text => Ok(self.text()),
+            NotesFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .wrap_err("Failed to serialize the release notes"),
+        }
+    }
+
+    pub fn markdown(&self) -> String {
+        let mut notes = format!("## {}\n", self.title);
+        if self.sections.is_empty() {
+            notes.push_str("\nNo changes\n");
+        }
+        for section in &self.sections {
+            notes.push_str(&format!("\n### {}\n\n", section.title));
+            for entry in &section.entries {
+                let scope = entry
+                    .scope
+                    .as_ref()
+                    .map(|scope| format!("**{}:** ", scope))
+                    .unwrap_or_default();
+                notes.push_str(&format!(
+                    "- {}{} ({})\n",
+                    scope,
+                    entry.description,
+                    entry.suffix(Reference::markdown)
+                ));
+            }
+        }
+        if !self.contributors.is_empty() {
+            notes.push_str("\n### Contributors\n\n");
+            for contributor in &self.contributors {
+                notes.push_str(&format!("- {}\n", contributor));
+            }
+        }
+        notes
+    }
+
+    pub fn text(&self) -> String {
+        let mut notes = format!("{}\n", self.title);
+        if self.sections.is_empty() {
+            notes.push_str("\nNo changes\n");
+        }
+        for section in &self.sections {
+            notes.push_str(&format!("\n{}\n", section.title));
+            for entry in &section.entries {
+                let scope = entry
+                    .scope
+                    .as_ref()
+                    .map(|scope| format!("{}: ", scope))
+                    .unwrap_or_default();
+                notes.push_str(&format!(
+                    "  - {}{} ({})\n",
+                    scope,
+                    entry.description,
+                    entry.suffix(|reference| reference.id.clone())
+                ));
+            }
+        }
+        if !self.contributors.is_empty() {
+            notes.push_str(&format!(
+                "\nContributors: {}\n",
+                self.contributors.join(", ")
+            ));
+        }
+        notes
+    }
+}
+
+/// The commits the notes are about, everything reachable from `until` but not from `since`
+#[derive(Debug, PartialEq, Eq)]
+pub struct NotesRange {
+    pub since: Option<String>,
+    pub until: String,
+    pub title: String,
+}
+
+/// The version of `tag` like it is written in the tag, the tag itself if it isn't a version with the tagprefix
+fn title<V>(tag: &str, tagprefix: &str, parse: impl Fn(&str) -> eyre::Result<V>) -> String {
+    tag.strip_prefix(tagprefix)
+        .filter(|version| parse(version).is_ok())
+        .unwrap_or(tag)
+        .to_string()
+}
+
+/// The tags with the tagprefix and a version that `parse` accepts, oldest first. The tags are kept as they are,
+/// so calendar versions with zero padded parts like `2026.01.0` still find their tag.
+fn release_tags<'a, V: Version>(
+    tags: &'a [String],
+    tagprefix: &str,
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> Vec<(V, &'a str)> {
+    let mut released: Vec<(V, &str)> = tags
+        .iter()
+        .filter_map(|tag| {
+            let version = parse(tag.strip_prefix(tagprefix)?).ok()?;
+            Some((version, tag.as_str()))
+        })
+        .collect();
+    released.sort();
+    released.dedup_by(|a, b| a.0 == b.0);
+    released
+}
+
+/// Resolves `unreleased` (everything since the latest release), a single release tag (everything since the release before)
+/// or a `<since>..<until>` revision range. `parse` reads the versions of the package's scheme.
+pub fn resolve_range<V: Version>(
+    range: &str,
+    tags: &[String],
+    tagprefix: &str,
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> eyre::Result<NotesRange> {
+    let released = release_tags(tags, tagprefix, &parse);
+    if range == "unreleased" {
+        return Ok(NotesRange {
+            since: released.last().map(|(_, tag)| tag.to_string()),
+            until: "HEAD".to_string(),
+            title: "Unreleased".to_string(),
+        });
+    }
+    if let Some((since, until)) = range.split_once("..") {
+        let until = Some(until).filter(|until| !until.is_empty());
+        return Ok(NotesRange {
+            since: Some(since.to_string()).filter(|since| !since.is_empty()),
+            until: until.unwrap_or("HEAD").to_string(),
+            title: until.map_or("Unreleased".to_string(), |until| {
+                title(until, tagprefix, &parse)
+            }),
+        });
+    }
+    let position = released
+        .iter()
+        .position(|(_, tag)| *tag == range)
+        .ok_or_else(|| eyre::eyre!("{} is not a released version", range))
+        .suggestion(format!(
+            "Pass unreleased, a tag with the tagprefix `{}` or a range like {}1.2.0..{}1.3.0",
+            tagprefix, tagprefix, tagprefix
+        ))?;
+    Ok(NotesRange {
+        since: position
+            .checked_sub(1)
+            .map(|previous| released[previous].1.to_string()),
+        until: range.to_string(),
+        title: title(range, tagprefix, &parse),
+    })
+}
+
+/// The first line of every changelog
+const CHANGELOG_HEADER: &str = "# Changelog\n";
+
+/// Puts the notes of a release on top of the sections of `changelog`, like [changelog] would after the release
+pub fn prepend(changelog: &str, section: &str) -> String {
+    let rest = changelog
+        .strip_prefix(CHANGELOG_HEADER)
+        .unwrap_or(changelog);
+    let mut prepended = format!("{}\n{}", CHANGELOG_HEADER, section);
+    if !rest.is_empty() && !rest.starts_with('\n') {
+        prepended.push('\n');
+    }
+    prepended.push_str(rest);
+    prepended
+}
+
+/// The whole changelog with a section for every release tag with the tagprefix, newest first.
+/// Every section holds the commits since the tag before, the result only depends on the tags and commits.
+/// Pre-releases don't get a section, their commits are part of the release that follows them.
+/// `parse` reads the versions of the package's scheme.
+#[instrument(level = "trace", skip(git, parse))]
+pub fn changelog<V: Version>(
+    git: &dyn GitBackend,
+    tagprefix: &str,
+    revert_type: &str,
+    links: &[ReferenceLink],
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> eyre::Result<String> {
+    let tags = git.tags().wrap_err("Failed to get git tags")?;
+    let released: Vec<_> = release_tags(&tags, tagprefix, &parse)
+        .into_iter()
+        .filter(|(version, _)| !version.is_prerelease())
+        .collect();
+    info!("Found {} release(s) with the tagprefix", released.len());
+    let mut sections = Vec::new();
+    let mut since: Option<&str> = None;
+    for (_, tag) in released {
+        let commits = git
+            .commits(since, tag, Path::new("."))
+            .wrap_err_with(|| format!("Failed to get the commits of {}", tag))?;
+        let notes = ReleaseNotes::new(
+            &title(tag, tagprefix, &parse),
+            &drop_reverted(commits),
+            revert_type,
+            links,
+        );
+        sections.push(notes.markdown());
+        since = Some(tag);
+    }
+    let mut changelog = CHANGELOG_HEADER.to_string();
+    for section in sections.iter().rev() {
+        changelog.push('\n');
+        changelog.push_str(section);
+    }
+    Ok(changelog)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scheme::CalendarVersion, semver::SemanticVersion};
+
+    use super::*;
+
+    fn commit(id: &str, author: &str, message: &str) -> Commit {
+        Commit {
+            id: id.to_string(),
+            author: author.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn notes() -> ReleaseNotes {
+        let commits = vec![
+            commit("eeeeeee5", "CI", "[Semantic release]: Release 1.2.1"),
+            commit("ddddddd4", "Max", "docs: readme"),
+            commit(
+                "ccccccc3",
+                "Jane Doe",
+                "feat(cli): new flag\n\nCo-authored-by: Max <max@example.com>",
+            ),
+            commit(
+                "bbbbbbb2",
+                "Max",
+                "fix: a bug (#78)\n\nCloses #12\nRefs: PROJ-456",
+            ),
+            commit(
+                "aaaaaaa1",
+                "Jane Doe",
+                "refactor: api\n\nBREAKING CHANGE: gone",
+            ),
+        ];
+        let links = [ReferenceLink {
+            prefix: "#".to_string(),
+            url: "https://git.example.com/org/repo/issues/{id}".to_string(),
+        }];
+        ReleaseNotes::new("1.3.0", &commits, "fix", &links)
+    }
+
+    #[test]
+    fn markdown_notes() {
+        assert_eq!(
+            notes().render(NotesFormat::Markdown).unwrap(),
+            "## 1.3.0\n\n### Breaking changes\n\n- api (aaaaaaa)\n\n### Features\n\n- **cli:** new flag (ccccccc)\n\n### Bug fixes\n\n- a bug (bbbbbbb, [#78](https://git.example.com/org/repo/issues/78), [#12](https://git.example.com/org/repo/issues/12), PROJ-456)\n\n### Other changes\n\n- readme (ddddddd)\n\n### Contributors\n\n- Jane Doe\n- Max\n"
+        );
+    }
+
+    #[test]
+    fn text_and_json_notes() {
+        let text = notes().render(NotesFormat::Text).unwrap();
+        assert!(text.starts_with("1.3.0\n\nBreaking changes\n  - api (aaaaaaa)\n"));
+        assert!(text.contains("\n  - a bug (bbbbbbb, #78, #12, PROJ-456)\n"));
+        assert!(text.ends_with("\nContributors: Jane Doe, Max\n"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&notes().render(NotesFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["sections"][1]["title"], "Features");
+        assert_eq!(
+            json["sections"][1]["entries"][0]["authors"],
+            serde_json::json!(["Jane Doe", "Max"])
+        );
+        assert_eq!(json["sections"][1]["entries"][0]["type"], "feat");
+        assert_eq!(
+            json["sections"][2]["entries"][0]["references"][2],
+            serde_json::json!({"id": "PROJ-456", "url": null})
+        );
+    }
+
+    #[test]
+    fn prepend_sections() {
+        let section = "## 1.3.0\n\n- new\n";
+        assert_eq!(prepend("", section), "# Changelog\n\n## 1.3.0\n\n- new\n");
+        assert_eq!(
+            prepend("# Changelog\n\n## 1.2.0\n\n- old\n", section),
+            "# Changelog\n\n## 1.3.0\n\n- new\n\n## 1.2.0\n\n- old\n"
+        );
+        // a file without the header keeps what's in there below the new section
+        assert_eq!(
+            prepend("notes\n", section),
+            "# Changelog\n\n## 1.3.0\n\n- new\n\nnotes\n"
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let tags: Vec<String> = ["v1.2.0", "v1.3.0", "other"].map(str::to_string).to_vec();
+        let semver = |raw: &str| raw.parse::<SemanticVersion>();
+        let range = |range| resolve_range(range, &tags, "v", semver).unwrap();
+        assert_eq!(
+            range("unreleased"),
+            NotesRange {
+                since: Some("v1.3.0".to_string()),
+                until: "HEAD".to_string(),
+                title: "Unreleased".to_string(),
+            }
+        );
+        assert_eq!(
+            range("v1.3.0"),
+            NotesRange {
+                since: Some("v1.2.0".to_string()),
+                until: "v1.3.0".to_string(),
+                title: "1.3.0".to_string(),
+            }
+        );
+        assert_eq!(
+            range("v1.2.0..v1.3.0"),
+            NotesRange {
+                since: Some("v1.2.0".to_string()),
+                until: "v1.3.0".to_string(),
+                title: "1.3.0".to_string(),
+            }
+        );
+        assert_eq!(range("v1.2.0..").title, "Unreleased");
+
+        // calendar versions keep their padding, the tags are read with the scheme of the package
+        let format = "YYYY.0M".parse().unwrap();
+        let calver = |raw: &str| CalendarVersion::parse(raw, &format);
+        let tags: Vec<String> = ["v2026.09", "v2026.10", "v1.2.3"]
+            .map(str::to_string)
+            .to_vec();
+        let range = resolve_range("v2026.10", &tags, "v", calver).unwrap();
+        assert_eq!(range.since.as_deref(), Some("v2026.09"));
+        assert_eq!(range.title, "2026.10");
+        assert!(resolve_range("v1.2.3", &tags, "v", calver).is_err());
+        assert!(resolve_range("v2026.10", &tags, "v", semver).is_err());
+    }
+}
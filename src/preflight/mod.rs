@@ -0,0 +1,378 @@
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use color_eyre::{eyre, Help};
+use console::style;
+use tracing::{error, info, instrument, warn};
+
+use crate::{channel::find_branch, config::Config, git::backend::GitBackend};
+
+/// Flags to skip single pre-flight checks
+#[derive(Args, Debug, Default, Clone, Copy)]
+pub struct Allow {
+    /// Release even if there are uncommitted changes besides the configured files
+    #[arg(long, default_value_t = false)]
+    pub allow_dirty: bool,
+    /// Release from a detached HEAD
+    #[arg(long, default_value_t = false)]
+    pub allow_detached: bool,
+    /// Release from a branch that doesn't match the configured branches
+    #[arg(long, default_value_t = false)]
+    pub allow_any_branch: bool,
+    /// Release a stable version from a branch or detached HEAD that none of the configured branches match
+    #[arg(long, default_value_t = false)]
+    pub allow_unmatched_stable: bool,
+    /// Release while a rebase, merge or similar operation is unfinished
+    #[arg(long, default_value_t = false)]
+    pub allow_in_progress: bool,
+    /// Release even if the branch is behind its upstream
+    #[arg(long, default_value_t = false)]
+    pub allow_behind: bool,
+    /// Release even if the tag for the new version exists already
+    #[arg(long, default_value_t = false)]
+    pub allow_existing_tag: bool,
+    /// Release without a git identity to commit with
+    #[arg(long, default_value_t = false)]
+    pub allow_missing_identity: bool,
+}
+
+#[derive(Debug)]
+struct Failure {
+    problem: String,
+    suggestion: String,
+}
+
+impl Failure {
+    fn new(problem: String, suggestion: &str) -> Option<Failure> {
+        Some(Failure {
+            problem,
+            suggestion: suggestion.to_string(),
+        })
+    }
+}
+
+fn normalize(path: &PathBuf) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.clone())
+}
+
+fn check_clean(git: &dyn GitBackend, release_files: &[PathBuf]) -> eyre::Result<Option<Failure>> {
+    let release_files: Vec<PathBuf> = release_files.iter().map(normalize).collect();
+    let changed: Vec<String> = git
+        .changed_files()?
+        .iter()
+        .map(normalize)
+        .filter(|file| !release_files.contains(file))
+        .map(|file| file.display().to_string())
+        .collect();
+    if changed.is_empty() {
+        return Ok(None);
+    }
+    Ok(Failure::new(
+        format!(
+            "The working tree has uncommitted changes: {}",
+            changed.join(", ")
+        ),
+        "Commit or stash them first, or pass --allow-dirty",
+    ))
+}
+
+fn check_branch(
+    git: &dyn GitBackend,
+    config: &Config,
+    allow: &Allow,
+) -> eyre::Result<Option<Failure>> {
+    let Some(branch) = git.current_branch()? else {
+        if allow.allow_detached {
+            return Ok(None);
+        }
+        return Ok(Failure::new(
+            "HEAD is detached".to_string(),
+            "Check out the branch you want to release from, or pass --allow-detached",
+        ));
+    };
+    if allow.allow_any_branch || config.branches.is_empty() {
+        return Ok(None);
+    }
+    if find_branch(&config.branches, &branch)?.is_some() {
+        return Ok(None);
+    }
+    let patterns: Vec<&str> = config
+        .branches
+        .iter()
+        .map(|branch| branch.name.as_str())
+        .collect();
+    Ok(Failure::new(
+        format!(
+            "Releases are not allowed from the branch {}, only from {}",
+            branch,
+            patterns.join(", ")
+        ),
+        "Switch to one of the configured branches, or pass --allow-any-branch",
+    ))
+}
+
+fn check_in_progress(git: &dyn GitBackend) -> eyre::Result<Option<Failure>> {
+    let Some(operation) = git.operation_in_progress()? else {
+        return Ok(None);
+    };
+    Ok(Failure::new(
+        format!("A {} is in progress", operation),
+        "Finish or abort it first (e.g. git rebase --continue), or pass --allow-in-progress",
+    ))
+}
+
+fn check_upstream(git: &dyn GitBackend) -> eyre::Result<Option<Failure>> {
+    match git.upstream_divergence()? {
+        Some((_, behind)) if behind > 0 => Ok(Failure::new(
+            format!("The branch is {} commit(s) behind its upstream", behind),
+            "Pull the missing commits first (e.g. git pull --rebase), or pass --allow-behind",
+        )),
+        _ => Ok(None),
+    }
+}
+
+fn check_tag(git: &dyn GitBackend, tag: &str) -> eyre::Result<Option<Failure>> {
+    if !git.tags()?.iter().any(|existing| existing == tag) {
+        return Ok(None);
+    }
+    Ok(Failure::new(
+        format!("The tag {} exists already", tag),
+        "Check if the release already happened or delete the tag, or pass --allow-existing-tag",
+    ))
+}
+
+fn check_identity(git: &dyn GitBackend) -> eyre::Result<Option<Failure>> {
+    if git.committer()?.is_some() {
+        return Ok(None);
+    }
+    Ok(Failure::new(
+        "There is no git identity to create the release commit with".to_string(),
+        "Configure one with git config user.name and git config user.email, or pass --allow-missing-identity",
+    ))
+}
+
+/// Makes sure the repository is in a state that allows a clean release before anything gets changed.
+/// `release_files` are the files that will be changed by the release, they may be dirty already.
+/// The tag check is skipped if the commit doesn't get a `tag`.
+/// During a dry run failed checks are only reported.
+#[instrument(level = "trace", skip(git, config))]
+pub fn run_checks(
+    git: &dyn GitBackend,
+    config: &Config,
+    allow: &Allow,
+    release_files: &[PathBuf],
+    tag: Option<&str>,
+    dryrun: bool,
+) -> eyre::Result<()> {
+    info!("Running pre-flight checks");
+    let mut failures = Vec::new();
+    if !allow.allow_dirty {
+        failures.extend(check_clean(git, release_files)?);
+    }
+    failures.extend(check_branch(git, config, allow)?);
+    if !allow.allow_in_progress {
+        failures.extend(check_in_progress(git)?);
+    }
+    if !allow.allow_behind {
+        failures.extend(check_upstream(git)?);
+    }
+    if let Some(tag) = tag.filter(|_| !allow.allow_existing_tag) {
+        failures.extend(check_tag(git, tag)?);
+    }
+    if !allow.allow_missing_identity {
+        failures.extend(check_identity(git)?);
+    }
+
+    if failures.is_empty() {
+        info!("All pre-flight checks passed");
+        return Ok(());
+    }
+    if dryrun {
+        for failure in &failures {
+            warn!("{}", style(&failure.problem).bold());
+            warn!("  {}", failure.suggestion);
+        }
+        warn!("Dry run is active, ignoring the failed pre-flight checks");
+        return Ok(());
+    }
+    let mut report = eyre::eyre!("{} pre-flight check(s) failed", failures.len());
+    for failure in failures {
+        error!("{}", style(&failure.problem).bold());
+        report = report.suggestion(failure.suggestion);
+    }
+    Err(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, process::Command};
+
+    use tempfile::TempDir;
+    use toml_edit::{de::from_document, Document};
+
+    use crate::{
+        git::backend::{open_backend, BackendKind},
+        testing::{self, commit, git},
+    };
+
+    use super::*;
+
+    /// A repository with a tagged Cargo.toml
+    fn tagged() -> TempDir {
+        let dir = testing::repo();
+        let path = dir.path();
+        fs::write(path.join("Cargo.toml"), "").unwrap();
+        commit(path, "chore: initial");
+        git(path, &["tag", "v1.0.0"]);
+        dir
+    }
+
+    fn config(branches: &str) -> Config {
+        let config = format!(
+            "subpath = \".\"\ntagprefix = \"v\"\nbranches = {}\nfiles = []",
+            branches
+        );
+        from_document(config.parse::<Document>().unwrap()).unwrap()
+    }
+
+    fn failures(directory: &Path, config: &Config, allow: &Allow, tag: &str) -> Vec<String> {
+        let git = open_backend(BackendKind::Gitoxide, directory).unwrap();
+        let release_files = vec![directory.join("Cargo.toml")];
+        [
+            check_clean(git.as_ref(), &release_files).unwrap(),
+            check_branch(git.as_ref(), config, allow).unwrap(),
+            check_in_progress(git.as_ref()).unwrap(),
+            check_upstream(git.as_ref()).unwrap(),
+            check_tag(git.as_ref(), tag).unwrap(),
+            check_identity(git.as_ref()).unwrap(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|failure| failure.problem)
+        .collect()
+    }
+
+    /// Whether [run_checks] stops a release that would tag v1.1.0
+    fn refused(directory: &Path, config: &Config, allow: &Allow) -> bool {
+        let git = open_backend(BackendKind::Gitoxide, directory).unwrap();
+        let release_files = vec![directory.join("Cargo.toml")];
+        run_checks(
+            git.as_ref(),
+            config,
+            allow,
+            &release_files,
+            Some("v1.1.0"),
+            false,
+        )
+        .is_err()
+    }
+
+    #[test]
+    fn clean_repository_passes() {
+        let repo = tagged();
+        fs::write(repo.path().join("Cargo.toml"), "changed").unwrap();
+        let config = config("[\"main\", \"release/*\"]");
+        assert!(failures(repo.path(), &config, &Allow::default(), "v1.1.0").is_empty());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let repo = tagged();
+        fs::write(repo.path().join("other"), "untracked").unwrap();
+        let config = config("[\"release/*\"]");
+        let failures = failures(repo.path(), &config, &Allow::default(), "v1.0.0");
+        assert_eq!(failures.len(), 3, "{:?}", failures);
+        assert!(failures[0].contains("other"));
+        assert!(failures[1].contains("branch main"));
+        assert!(failures[2].contains("v1.0.0"));
+
+        assert!(refused(repo.path(), &config, &Allow::default()));
+        let allow = Allow {
+            allow_dirty: true,
+            allow_any_branch: true,
+            ..Default::default()
+        };
+        assert!(!refused(repo.path(), &config, &allow));
+        // the tag of the release exists already
+        git(repo.path(), &["tag", "v1.1.0"]);
+        assert!(refused(repo.path(), &config, &allow));
+        let allow = Allow {
+            allow_existing_tag: true,
+            ..allow
+        };
+        assert!(!refused(repo.path(), &config, &allow));
+    }
+
+    #[test]
+    fn detached_head() {
+        let repo = tagged();
+        git(repo.path(), &["checkout", "--quiet", "--detach"]);
+        let config = config("[]");
+        let failures_found = failures(repo.path(), &config, &Allow::default(), "v1.1.0");
+        assert_eq!(failures_found, vec!["HEAD is detached".to_string()]);
+        let allow = Allow {
+            allow_detached: true,
+            ..Default::default()
+        };
+        assert!(failures(repo.path(), &config, &allow, "v1.1.0").is_empty());
+    }
+
+    #[test]
+    fn operation_in_progress() {
+        let repo = tagged();
+        let path = repo.path();
+        git(path, &["checkout", "--quiet", "-b", "side"]);
+        fs::write(path.join("Cargo.toml"), "side").unwrap();
+        commit(path, "fix: on the side");
+        git(path, &["checkout", "--quiet", "main"]);
+        fs::write(path.join("Cargo.toml"), "main").unwrap();
+        commit(path, "fix: on main");
+        // the conflict stops the merge halfway
+        let merge = Command::new("git")
+            .args(["merge", "--quiet", "side"])
+            .current_dir(path)
+            .output()
+            .unwrap();
+        assert!(!merge.status.success());
+
+        let config = config("[]");
+        let failures_found = failures(path, &config, &Allow::default(), "v1.1.0");
+        assert_eq!(failures_found, vec!["A merge is in progress".to_string()]);
+        assert!(refused(path, &config, &Allow::default()));
+        let allow = Allow {
+            allow_in_progress: true,
+            ..Default::default()
+        };
+        assert!(!refused(path, &config, &allow));
+    }
+
+    #[test]
+    fn behind_upstream() {
+        let upstream = tagged();
+        let clone = TempDir::new().unwrap();
+        let path = clone.path();
+        git(
+            upstream.path(),
+            &["clone", "--quiet", ".", path.to_str().unwrap()],
+        );
+        git(path, &["config", "user.name", "Test"]);
+        git(path, &["config", "user.email", "test@example.com"]);
+        fs::write(upstream.path().join("Cargo.toml"), "newer").unwrap();
+        commit(upstream.path(), "fix: upstream");
+        let config = config("[]");
+        assert!(!refused(path, &config, &Allow::default()));
+
+        git(path, &["fetch", "--quiet"]);
+        let failures_found = failures(path, &config, &Allow::default(), "v1.1.0");
+        assert_eq!(
+            failures_found,
+            vec!["The branch is 1 commit(s) behind its upstream".to_string()]
+        );
+        assert!(refused(path, &config, &Allow::default()));
+        let allow = Allow {
+            allow_behind: true,
+            ..Default::default()
+        };
+        assert!(!refused(path, &config, &allow));
+    }
+}
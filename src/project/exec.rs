@@ -0,0 +1,246 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use color_eyre::{
+    eyre::{self, ContextCompat, WrapErr},
+    Help,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+
+use crate::{
+    command::CommandError,
+    config::{KeyValue, ProjectFile},
+    semver::SemanticVersion,
+};
+
+use super::VersionFile;
+
+/// Version of the protocol, sent with every request
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum Method {
+    ReadVersion,
+    UpdateProject {
+        version: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        values: Vec<KeyValue>,
+    },
+}
+
+#[derive(Serialize, Debug)]
+struct Request<'a> {
+    protocol: u32,
+    #[serde(flatten)]
+    method: Method,
+    path: &'a Path,
+    key: &'a str,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Response {
+    version: Option<String>,
+    content: Option<String>,
+    error: Option<String>,
+}
+
+/// A project file handled by an external plugin executable.
+///
+/// The plugin gets one JSON request on stdin and answers with one JSON response on stdout:
+///
+/// ```text
+/// {"protocol": 1, "method": "read_version", "path": "/repo/app.desc", "key": "version"}
+/// {"version": "1.2.3"}
+///
+/// {"protocol": 1, "method": "update_project", "path": "/repo/app.desc", "key": "version", "version": "1.3.0"}
+/// {"content": "<the whole file with the new version>"}
+/// ```
+///
+/// Files with `values` get them rendered in the `update_project` request, plugins that can't set them should fail:
+///
+/// ```text
+/// {"protocol": 1, "method": "update_project", "version": "1.3.0", "values": [{"key": "image", "value": "app:1.3.0"}], ...}
+/// ```
+///
+/// The plugin must not change the file itself, semantic-release writes the returned content.
+/// Problems are reported with `{"error": "<message>"}` or a non-zero exit code, stderr ends up in the error.
+#[derive(Debug)]
+pub struct Exec {
+    filepath: PathBuf,
+    executable: PathBuf,
+    /// The directory the plugin runs in
+    subpath: PathBuf,
+    config: ProjectFile,
+}
+
+impl Exec {
+    #[instrument(level = "trace", name = "exec::call", skip(self))]
+    fn call(&self, method: Method) -> eyre::Result<Response> {
+        let request = serde_json::to_string(&Request {
+            protocol: PROTOCOL_VERSION,
+            method,
+            path: &self.filepath,
+            key: &self.config.key,
+        })?;
+        let command_line = self.executable.display().to_string();
+        debug!("Sending {} to {}", request, command_line);
+        let mut child = Command::new(&self.executable)
+            .current_dir(&self.subpath)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| CommandError::Spawn {
+                command_line: command_line.clone(),
+                cwd: self.subpath.clone(),
+                source,
+            })?;
+        child
+            .stdin
+            .take()
+            .wrap_err("Failed to open the stdin of the plugin")?
+            .write_all(request.as_bytes())
+            .wrap_err("Failed to send the request to the plugin")?;
+        let output = child
+            .wait_with_output()
+            .wrap_err("Failed to read the response of the plugin")?;
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let response: Option<Response> = serde_json::from_slice(&output.stdout).ok();
+        if let Some(error) = response
+            .as_ref()
+            .and_then(|response| response.error.clone())
+        {
+            eyre::bail!("The plugin {} failed: {}", command_line, error);
+        }
+        if !output.status.success() {
+            return Err(CommandError::Failed {
+                command_line,
+                cwd: self.subpath.clone(),
+                exit_code: output.status.code(),
+                stderr,
+            }
+            .into());
+        }
+        response
+            .wrap_err_with(|| {
+                format!(
+                    "The plugin {} didn't answer with a JSON object: {}",
+                    command_line,
+                    String::from_utf8_lossy(&output.stdout).trim()
+                )
+            })
+            .suggestion(
+                "Plugins have to print exactly one JSON response to stdout, logs go to stderr",
+            )
+    }
+}
+
+/// The subpath, `filepath` is `config.path` inside of it
+fn subpath_of(filepath: &Path, config: &ProjectFile) -> PathBuf {
+    let depth = Path::new(&config.path).components().count();
+    filepath
+        .ancestors()
+        .nth(depth)
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+impl VersionFile for Exec {
+    #[instrument(level = "trace", name = "exec::new")]
+    fn new(filepath: &Path, config: &ProjectFile) -> eyre::Result<Box<Self>> {
+        let command = config
+            .command
+            .as_ref()
+            .wrap_err_with(|| format!("{} has the exec type but no command", config.path))
+            .suggestion("Set command to the plugin executable")?;
+        let subpath = subpath_of(filepath, config);
+        let executable = if command.contains('/') || command.contains(std::path::MAIN_SEPARATOR) {
+            subpath.join(command)
+        } else {
+            PathBuf::from(command)
+        };
+        Ok(Box::new(Exec {
+            filepath: filepath.to_path_buf(),
+            executable,
+            subpath,
+            config: config.clone(),
+        }))
+    }
+
+    fn read_version(&self) -> eyre::Result<String> {
+        self.call(Method::ReadVersion)?
+            .version
+            .wrap_err("The plugin didn't answer with a version")
+    }
+
+    fn update_project(&mut self, semver: &SemanticVersion) -> eyre::Result<String> {
+        self.update_values(&semver.to_string(), &[])
+    }
+
+    fn update_values(&mut self, version: &str, values: &[KeyValue]) -> eyre::Result<String> {
+        info!(
+            "Updating {} with {}",
+            self.config.path,
+            self.executable.display()
+        );
+        self.call(Method::UpdateProject {
+            version: version.to_string(),
+            values: values.to_vec(),
+        })?
+        .content
+        .wrap_err("The plugin didn't answer with the new content")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectType;
+
+    #[test]
+    fn requests() {
+        let request = Request {
+            protocol: PROTOCOL_VERSION,
+            method: Method::UpdateProject {
+                version: "1.3.0".to_string(),
+                values: vec![KeyValue {
+                    key: "image".to_string(),
+                    value: "app:1.3.0".to_string(),
+                }],
+            },
+            path: Path::new("/repo/app.desc"),
+            key: "version",
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"protocol":1,"method":"update_project","version":"1.3.0","values":[{"key":"image","value":"app:1.3.0"}],"path":"/repo/app.desc","key":"version"}"#
+        );
+    }
+
+    #[test]
+    fn executable_relative_to_subpath() {
+        let config = ProjectFile {
+            path: "deploy/app.desc".to_string(),
+            key: "version".to_string(),
+            project_type: ProjectType::Exec,
+            command: Some("./plugins/desc".to_string()),
+            values: Vec::new(),
+        };
+        let exec = Exec::new(Path::new("/repo/pkg/deploy/app.desc"), &config).unwrap();
+        assert_eq!(exec.subpath, Path::new("/repo/pkg"));
+        assert_eq!(exec.executable, Path::new("/repo/pkg/./plugins/desc"));
+
+        let config = ProjectFile {
+            command: Some("desc-plugin".to_string()),
+            ..config
+        };
+        let exec = Exec::new(Path::new("/repo/pkg/deploy/app.desc"), &config).unwrap();
+        assert_eq!(exec.executable, Path::new("desc-plugin"));
+    }
+}
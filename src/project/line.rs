@@ -0,0 +1,198 @@
+use std::{fs, ops::Range, path::Path};
+
+use crate::{
+    config::{KeyValue, ProjectFile},
+    semver::SemanticVersion,
+};
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use tracing::{debug, info, instrument};
+
+use super::VersionFile;
+
+/// Marks the version in the `key` of a file
+const VERSION: &str = "{version}";
+/// Marks the value in the `key` of a value
+const VALUE: &str = "{value}";
+
+/// Any text file, the key is the line that holds the version like `appVersion: "{version}"`.
+/// Works for YAML files like a Helm `Chart.yaml` or the image of a compose file without parsing them.
+#[derive(Debug)]
+pub struct Line {
+    content: String,
+    config: ProjectFile,
+}
+
+/// The text around the placeholder of `pattern`
+fn split_pattern<'a>(pattern: &'a str, placeholder: &str) -> eyre::Result<(&'a str, &'a str)> {
+    let (before, after) = pattern
+        .split_once(placeholder)
+        .ok_or_else(|| eyre::eyre!("The key {} has no {}", pattern, placeholder))
+        .suggestion(format!(
+            "Write the line like it is in the file with {} where the value is, e.g. `appVersion: \"{}\"`",
+            placeholder, placeholder
+        ))?;
+    if after.contains(placeholder) {
+        eyre::bail!("The key {} has more than one {}", pattern, placeholder);
+    }
+    Ok((before.trim_start(), after))
+}
+
+impl Line {
+    pub fn parse(filecontent: &str, config: &ProjectFile) -> eyre::Result<Self> {
+        let line = Line {
+            content: filecontent.to_string(),
+            config: config.clone(),
+        };
+        // fails early for a key without a version
+        split_pattern(&line.config.key, VERSION)?;
+        Ok(line)
+    }
+
+    /// Where the value is in the line that starts like `pattern`, without looking at the indentation.
+    /// The least indented line wins, e.g. the `version` of a chart instead of the ones of its dependencies.
+    fn find(&self, pattern: &str, placeholder: &str) -> eyre::Result<Range<usize>> {
+        let (before, after) = split_pattern(pattern, placeholder)?;
+        let mut matches: Vec<(usize, Range<usize>)> = Vec::new();
+        let mut offset = 0;
+        for line in self.content.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+            let body = line.trim_start();
+            let indentation = line.len() - body.len();
+            let Some(rest) = body.strip_prefix(before) else {
+                continue;
+            };
+            // the value ends where the text after the placeholder starts, anything after that like a comment stays
+            let end = if after.is_empty() {
+                rest.find(char::is_whitespace).unwrap_or(rest.len())
+            } else {
+                match rest.find(after) {
+                    Some(end) => end,
+                    None => continue,
+                }
+            };
+            let value = &rest[..end];
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                continue;
+            }
+            let value_start = start + indentation + before.len();
+            matches.push((indentation, value_start..value_start + value.len()));
+        }
+        let Some(least) = matches.iter().map(|(indentation, _)| *indentation).min() else {
+            return Err(eyre::eyre!("No line looks like {}", pattern));
+        };
+        matches.retain(|(indentation, _)| *indentation == least);
+        if matches.len() > 1 {
+            return Err(eyre::eyre!(
+                "{} lines with the same indentation look like {}",
+                matches.len(),
+                pattern
+            ))
+            .suggestion("Add more of the line to the key, e.g. the image name");
+        }
+        let (_, range) = matches.remove(0);
+        debug!("{} matches at {:?}", pattern, range);
+        Ok(range)
+    }
+
+    fn replace(&mut self, pattern: &str, placeholder: &str, value: &str) -> eyre::Result<()> {
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            eyre::bail!("The value {:?} is empty or contains whitespace", value);
+        }
+        let range = self.find(pattern, placeholder)?;
+        self.content.replace_range(range, value);
+        Ok(())
+    }
+}
+
+impl VersionFile for Line {
+    #[instrument(level = "trace", name = "line::new")]
+    fn new(filepath: &Path, config: &ProjectFile) -> eyre::Result<Box<Self>> {
+        let filecontent = fs::read_to_string(filepath).context("Failed to read project file")?;
+        Ok(Box::new(Line::parse(&filecontent, config)?))
+    }
+
+    #[instrument(level = "trace", name = "line::read_version", skip(self))]
+    fn read_version(&self) -> eyre::Result<String> {
+        let range = self.find(&self.config.key, VERSION)?;
+        Ok(self.content[range].to_string())
+    }
+
+    #[instrument(level = "trace", name = "line::update_project", skip(self))]
+    fn update_project(&mut self, semver: &SemanticVersion) -> eyre::Result<String> {
+        self.update_values(&semver.to_string(), &[])
+    }
+
+    #[instrument(level = "trace", name = "line::update_values", skip(self))]
+    fn update_values(&mut self, version: &str, values: &[KeyValue]) -> eyre::Result<String> {
+        info!("Updating the lines");
+        let key = self.config.key.clone();
+        self.replace(&key, VERSION, version)?;
+        for value in values {
+            self.replace(&value.key, VALUE, &value.value)
+                .wrap_err_with(|| format!("Failed to set {}", value.key))?;
+        }
+        Ok(self.content.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ProjectType;
+
+    use super::*;
+
+    fn line(key: &str, content: &str) -> Line {
+        let config = ProjectFile {
+            path: "Chart.yaml".to_string(),
+            key: key.to_string(),
+            project_type: ProjectType::Line,
+            command: None,
+            values: Vec::new(),
+        };
+        Line::parse(content, &config).unwrap()
+    }
+
+    #[test]
+    fn chart_versions() {
+        let chart = "apiVersion: v2\nname: app\nversion: 0.3.0 # the chart\nappVersion: \"1.0.0\"\ndependencies:\n  - name: redis\n    version: 17.3.0\n";
+        let mut file = line("version: {version}", chart);
+        assert_eq!(file.read_version().unwrap(), "0.3.0");
+        let values = [KeyValue {
+            key: "appVersion: \"{value}\"".to_string(),
+            value: "1.4.0".to_string(),
+        }];
+        assert_eq!(
+            file.update_values("0.4.0", &values).unwrap(),
+            "apiVersion: v2\nname: app\nversion: 0.4.0 # the chart\nappVersion: \"1.4.0\"\ndependencies:\n  - name: redis\n    version: 17.3.0\n"
+        );
+    }
+
+    #[test]
+    fn ambiguous_lines() {
+        let compose = "services:\n  app:\n    image: registry/app:1.0.0\n  worker:\n    image: registry/app:1.0.0\n";
+        let err = line("image: registry/app:{version}", compose)
+            .read_version()
+            .unwrap_err();
+        assert!(err.to_string().starts_with("2 lines"), "{}", err);
+        let err = line("image: registry/web:{version}", compose)
+            .read_version()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No line looks like image: registry/web:{version}"
+        );
+        // the text after the placeholder has to be there as well
+        assert!(line("name: \"{version}\"", "name: app\n")
+            .read_version()
+            .is_err());
+        let config = ProjectFile {
+            key: "version".to_string(),
+            ..line("{version}", "").config
+        };
+        assert!(Line::parse("", &config).is_err());
+    }
+}
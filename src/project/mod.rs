@@ -1,34 +1,117 @@
+pub mod exec;
+pub mod line;
 pub mod toml;
 
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::Path};
 
-use color_eyre::eyre::{self, WrapErr};
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
 use console::style;
 use serde_json::Value;
 use tracing::{debug, info, instrument, warn};
 
 use crate::{
-    config::{ProjectFile, ProjectType},
+    config::{KeyValue, ProjectFile, ProjectType},
+    scheme::Version,
     semver::SemanticVersion,
 };
 
-use self::toml::Toml;
+use self::{exec::Exec, line::Line, toml::Toml};
 
 pub trait VersionFile {
-    fn new(filepath: &PathBuf, config: &ProjectFile) -> eyre::Result<Box<Self>>
+    fn new(filepath: &Path, config: &ProjectFile) -> eyre::Result<Box<Self>>
     where
         Self: Sized;
     fn read_version(&self) -> eyre::Result<String>;
     fn update_project(&mut self, semver: &SemanticVersion) -> eyre::Result<String>;
+    /// Like [VersionFile::update_project], but for a version of any scheme and also sets the `values` of the file, rendered already.
+    /// Types that only support semantic versions and no values only have to implement `update_project`.
+    fn update_values(&mut self, version: &str, values: &[KeyValue]) -> eyre::Result<String> {
+        if let Some(value) = values.first() {
+            eyre::bail!("This project type can't set other keys like {}", value.key);
+        }
+        let semver: SemanticVersion = version.parse()?;
+        if semver.to_string() != version {
+            eyre::bail!(
+                "This project type only supports semantic versions, not {}",
+                version
+            );
+        }
+        self.update_project(&semver)
+    }
+}
+
+/// Replaces the placeholders of a value template: `{version}` with `version` and `{tag:<tagprefix>}`
+/// with the highest stable version of the `tags` with the tagprefix, written like in the tag.
+/// The tags are parsed and compared with `parse`, the scheme of the package that is released.
+pub fn render_value<V: Version>(
+    template: &str,
+    version: &str,
+    tags: &[String],
+    parse: impl Fn(&str) -> eyre::Result<V>,
+) -> eyre::Result<String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let (placeholder, after) = rest[start + 1..]
+            .split_once('}')
+            .ok_or_else(|| eyre::eyre!("The value {} has an unclosed {{", template))?;
+        if placeholder == "version" {
+            rendered.push_str(version);
+        } else if let Some(tagprefix) = placeholder.strip_prefix("tag:") {
+            let (_, released) = tags
+                .iter()
+                .filter_map(|tag| tag.strip_prefix(tagprefix))
+                .filter_map(|raw| Some((parse(raw).ok()?, raw)))
+                .filter(|(version, _)| !version.is_prerelease())
+                .max_by(|(a, _), (b, _)| a.cmp(b))
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "There is no stable release with the tagprefix {}",
+                        tagprefix
+                    )
+                })?;
+            rendered.push_str(released);
+        } else {
+            return Err(eyre::eyre!(
+                "The value {} has the unknown placeholder {{{}}}",
+                template,
+                placeholder
+            ))
+            .suggestion("Use {version} or {tag:<tagprefix>}");
+        }
+        rest = after;
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
 }
 
 pub fn load_versionfile(
-    filepath: &PathBuf,
+    filepath: &Path,
     config: &ProjectFile,
 ) -> eyre::Result<Box<dyn VersionFile>> {
     match config.project_type {
         ProjectType::Json => Ok(Json::new(filepath, config)?),
         ProjectType::Toml => Ok(Toml::new(filepath, config)?),
+        ProjectType::Line => Ok(Line::new(filepath, config)?),
+        ProjectType::Exec => Ok(Exec::new(filepath, config)?),
+    }
+}
+
+/// Parses `content` as the type of `config` without touching the filesystem, e.g. to check an update.
+/// `None` for plugins, they only read files.
+pub fn parse_versionfile(
+    content: &str,
+    config: &ProjectFile,
+) -> eyre::Result<Option<Box<dyn VersionFile>>> {
+    match config.project_type {
+        ProjectType::Json => Ok(Some(Box::new(Json::parse(content, config)?))),
+        ProjectType::Toml => Ok(Some(Box::new(Toml::parse(content, config)?))),
+        ProjectType::Line => Ok(Some(Box::new(Line::parse(content, config)?))),
+        ProjectType::Exec => Ok(None),
     }
 }
 
@@ -38,39 +121,99 @@ pub struct Json {
     config: ProjectFile,
 }
 
+impl Json {
+    pub fn parse(filecontent: &str, config: &ProjectFile) -> eyre::Result<Self> {
+        let json: HashMap<String, serde_json::Value> = serde_json::from_str(filecontent)?;
+        debug!("json: {:?}", json);
+        let config = config.clone();
+        Ok(Json { json, config })
+    }
+}
+
 impl VersionFile for Json {
     #[instrument(level = "trace", name = "json::new")]
-    fn new(filepath: &PathBuf, config: &ProjectFile) -> eyre::Result<Box<Self>> {
+    fn new(filepath: &Path, config: &ProjectFile) -> eyre::Result<Box<Self>> {
         let filecontent = fs::read_to_string(filepath).context("Failed to read project file")?;
-        let json: HashMap<String, serde_json::Value> = serde_json::from_str(&filecontent)?;
-        debug!("json: {:?}", json);
-        let config = config.clone();
-        Ok(Box::new(Json { json, config }))
+        Ok(Box::new(Json::parse(&filecontent, config)?))
     }
 
     #[instrument(level = "trace", name = "json::read_version")]
     fn read_version(&self) -> eyre::Result<String> {
-        let version = self.json[&self.config.key].as_str().unwrap();
+        let version = self
+            .json
+            .get(&self.config.key)
+            .ok_or_else(|| eyre::eyre!("The key {} doesn't exist", self.config.key))?
+            .as_str()
+            .ok_or_else(|| eyre::eyre!("The value at {} is not a string", self.config.key))?;
         let version = version.to_string();
         Ok(version)
     }
 
     #[instrument(level = "trace", name = "json::update_project")]
     fn update_project(&mut self, semver: &SemanticVersion) -> eyre::Result<String> {
+        self.update_values(&semver.to_string(), &[])
+    }
+
+    #[instrument(level = "trace", name = "json::update_values")]
+    fn update_values(&mut self, version: &str, values: &[KeyValue]) -> eyre::Result<String> {
         info!("Updating JSON");
-        let key = self.config.key.to_string();
-        debug!(
-            "Trying to insert {} into key {}",
-            style(semver.to_string()).bold(),
-            style(&key).bold()
-        );
-        let _ = self
-            .json
-            .insert(key, Value::String(semver.to_string()))
-            .unwrap();
+        let version = version.to_string();
+        let pairs = [(&self.config.key, &version)]
+            .into_iter()
+            .chain(values.iter().map(|value| (&value.key, &value.value)));
+        for (key, value) in pairs {
+            debug!(
+                "Trying to insert {} into key {}",
+                style(value).bold(),
+                style(key).bold()
+            );
+            if !self.json.contains_key(key) {
+                eyre::bail!("The key {} doesn't exist", key);
+            }
+            self.json.insert(key.clone(), Value::String(value.clone()));
+        }
         let json = serde_json::to_string_pretty(&self.json)
             .context("Failed to turn the parsed object back into JSON")?;
         debug!("new json: {}", json);
         Ok(json)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::scheme::{CalendarFormat, CalendarVersion};
+
+    use super::*;
+
+    #[test]
+    fn value_templates() {
+        let version = "1.3.0";
+        let tags: Vec<String> = ["app-v2.0.0", "app-v2.1.0", "app-v2.2.0-rc.1", "v1.2.0"]
+            .map(str::to_string)
+            .to_vec();
+        let render = |template| {
+            render_value(template, version, &tags, |raw| {
+                raw.parse::<SemanticVersion>()
+            })
+        };
+        assert_eq!(
+            render("registry/app:{version}").unwrap(),
+            "registry/app:1.3.0"
+        );
+        assert_eq!(render("{tag:app-v}").unwrap(), "2.1.0");
+        assert_eq!(render("no placeholder").unwrap(), "no placeholder");
+        assert!(render("{tag:lib-v}").is_err());
+        assert!(render("{versoin}").is_err());
+        assert!(render("{version").is_err());
+
+        // calendar versions keep their padding and may have two parts
+        let format: CalendarFormat = "YYYY.0M".parse().unwrap();
+        let tags: Vec<String> = ["app-v2026.09", "app-v2026.10", "app-v2025.12"]
+            .map(str::to_string)
+            .to_vec();
+        let rendered = render_value("{tag:app-v}", version, &tags, |raw| {
+            CalendarVersion::parse(raw, &format)
+        });
+        assert_eq!(rendered.unwrap(), "2026.10");
+    }
+}
@@ -1,6 +1,9 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path};
 
-use crate::{config::ProjectFile, semver::SemanticVersion};
+use crate::{
+    config::{KeyValue, ProjectFile},
+    semver::SemanticVersion,
+};
 use color_eyre::eyre::{self, WrapErr};
 use console::style;
 use toml_edit::{Document, Item};
@@ -14,22 +17,25 @@ pub struct Toml {
     config: ProjectFile,
 }
 
+impl Toml {
+    pub fn parse(filecontent: &str, config: &ProjectFile) -> eyre::Result<Self> {
+        let toml = filecontent
+            .parse::<Document>()
+            .context("Failed to parse toml file")?;
+        let config = config.clone();
+        debug!("toml: {:?}", toml);
+        Ok(Toml { toml, config })
+    }
+}
+
 impl VersionFile for Toml {
     #[instrument(level = "trace", name = "toml::new")]
-    fn new(filepath: &PathBuf, config: &ProjectFile) -> eyre::Result<Box<Self>>
+    fn new(filepath: &Path, config: &ProjectFile) -> eyre::Result<Box<Self>>
     where
         Self: Sized,
     {
         let filecontent = fs::read_to_string(filepath).context("Failed to read project file")?;
-        let toml = filecontent
-            .parse::<Document>()
-            .context("Failed to parse toml file")?;
-        let config = config.clone();
-        debug!("toml: {:?}", toml);
-        Ok(Box::new(Toml {
-            toml,
-            config,
-        }))
+        Ok(Box::new(Toml::parse(&filecontent, config)?))
     }
 
     #[instrument(level = "trace", name = "toml::read_version", skip(self))]
@@ -47,10 +53,20 @@ impl VersionFile for Toml {
 
     #[instrument(level = "trace", name = "toml::update_project", skip(self))]
     fn update_project(&mut self, semver: &SemanticVersion) -> eyre::Result<String> {
+        self.update_values(&semver.to_string(), &[])
+    }
+
+    #[instrument(level = "trace", name = "toml::update_values", skip(self))]
+    fn update_values(&mut self, version: &str, values: &[KeyValue]) -> eyre::Result<String> {
         info!("Updating toml!");
         let path: Vec<&str> = self.config.key.split('.').collect();
         let path = path.as_slice();
-        update_version(self.toml.as_item_mut(), path, 0, &semver.to_string())?;
+        update_version(self.toml.as_item_mut(), path, 0, version)?;
+        for value in values {
+            let path: Vec<&str> = value.key.split('.').collect();
+            update_version(self.toml.as_item_mut(), &path, 0, &value.value)
+                .wrap_err_with(|| format!("Failed to set {}", value.key))?;
+        }
         Ok(self.toml.to_string())
     }
 }
@@ -85,7 +101,7 @@ fn get_version(node: &Item, desired_path: &[&str], current_index: usize) -> eyre
             style(desired_key).bold()
         );
         let node = node.as_array().unwrap();
-        let desired_key = usize::from_str_radix(desired_key, 10).with_context(|| format!("Error when trying to traverse the toml. Got an array, but {} is not a valid array index.", style(desired_key).bold()))?;
+        let desired_key = desired_key.parse::<usize>().with_context(|| format!("Error when trying to traverse the toml. Got an array, but {} is not a valid array index.", style(desired_key).bold()))?;
         match node.get(desired_key) {
             Some(node) => {
                 let node = Item::Value(node.clone());
@@ -117,7 +133,7 @@ fn update_version(
             "Item is a table or array, trying to get key {}",
             style(desired_key).bold()
         );
-        let key_as_usize = usize::from_str_radix(desired_key, 10);
+        let key_as_usize = desired_key.parse::<usize>();
         match key_as_usize {
             Ok(desired_key) => match node.get_mut(desired_key) {
                 Some(node) => {
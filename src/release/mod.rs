@@ -0,0 +1,789 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use color_eyre::{
+    eyre::{self, WrapErr},
+    Help,
+};
+use console::style;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    channel::{self, VersionOverride},
+    config::{Channel, Config, KeyValue},
+    consistency::{self, FileVersion, SyncTarget},
+    git::{
+        self,
+        backend::{open_backend, BackendKind, GitBackend, IndexEntry},
+        calc_bumplevel, BumpLevel,
+    },
+    hooks::{self, ReleaseContext, Stage},
+    notes::{self, ReleaseNotes},
+    preflight::{self, Allow},
+    project::{load_versionfile, render_value},
+    report::DryRunReport,
+    scheme::{CalendarVersion, Clock, SystemClock, Version},
+    semver::SemanticVersion,
+    transaction::{self, FileUpdate, Transaction},
+};
+
+/// How a [Releaser] releases, everything that doesn't come from the config
+#[derive(Debug, Clone)]
+pub struct ReleaseOptions {
+    pub git_backend: BackendKind,
+    /// The pre-flight checks to skip
+    pub allow: Allow,
+    /// Overrides the version the commits lead to
+    pub version_override: Option<VersionOverride>,
+    /// The date of calendar versions
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for ReleaseOptions {
+    fn default() -> Self {
+        ReleaseOptions {
+            git_backend: BackendKind::Gitoxide,
+            allow: Allow::default(),
+            version_override: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+/// Version of the plan files, plans of another version are refused
+pub const PLAN_FORMAT: u32 = 1;
+
+/// Everything a release is going to change, computed by [Releaser::plan] without touching anything.
+/// Plans can be written to disk and applied later, as long as HEAD doesn't move in between.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleasePlan {
+    pub format: u32,
+    /// Id of the commit the plan was computed at
+    pub head: String,
+    /// Versions are written like the scheme of the package prints them
+    pub previous_version: String,
+    pub next_version: String,
+    /// The level the commits lead to, without overrides like --bump
+    pub bumplevel: BumpLevel,
+    /// The new content of every project file that changes
+    pub updates: Vec<FileUpdate>,
+    pub commit_message: String,
+    pub previous_tag: String,
+    /// Missing if the commit doesn't get tagged, e.g. when syncing versions
+    pub tag: Option<String>,
+    /// The release notes of the commits as markdown, empty if there are none.
+    /// They get added to the top of the changelog file of the config.
+    pub changelog: String,
+}
+
+impl ReleasePlan {
+    /// Reads a plan written by [ReleasePlan::write]
+    pub fn read(path: &Path) -> eyre::Result<ReleasePlan> {
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read the plan {}", path.display()))?;
+        let plan: ReleasePlan = serde_json::from_str(&content)
+            .wrap_err_with(|| format!("{} is not a release plan", path.display()))?;
+        if plan.format != PLAN_FORMAT {
+            return Err(eyre::eyre!(
+                "The plan {} has the format {}, this version of semantic-release only knows {}",
+                path.display(),
+                plan.format,
+                PLAN_FORMAT
+            ))
+            .suggestion("Create the plan again with this version of semantic-release");
+        }
+        Ok(plan)
+    }
+
+    pub fn to_json(&self) -> eyre::Result<String> {
+        serde_json::to_string_pretty(self).wrap_err("Failed to serialize the plan")
+    }
+
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        fs::write(path, self.to_json()? + "\n")
+            .wrap_err_with(|| format!("Failed to write the plan to {}", path.display()))
+    }
+
+    /// What the hooks get to know about the release, `None` if the plan isn't a release
+    fn release_context(&self, subpath: &Path) -> Option<ReleaseContext> {
+        Some(ReleaseContext {
+            previous_version: self.previous_version.clone(),
+            next_version: self.next_version.clone(),
+            previous_tag: self.previous_tag.clone(),
+            tag: self.tag.clone()?,
+            bumplevel: self.bumplevel,
+            commit_message: self.commit_message.clone(),
+            subpath: subpath.to_path_buf(),
+            commit: None,
+        })
+    }
+
+    fn release_files(&self, subpath: &Path) -> Vec<PathBuf> {
+        self.updates
+            .iter()
+            .map(|update| update.filepath(subpath))
+            .collect()
+    }
+}
+
+/// Computes and applies releases of one config
+pub struct Releaser {
+    config: Config,
+    subpath: PathBuf,
+    git: Box<dyn GitBackend>,
+    options: ReleaseOptions,
+}
+
+impl Releaser {
+    /// A releaser for `config`, its subpath is relative to `directory`, usually the directory of the config file
+    #[instrument(level = "trace", skip(config))]
+    pub fn new(
+        config: Config,
+        directory: &Path,
+        options: ReleaseOptions,
+    ) -> eyre::Result<Releaser> {
+        let subpath = directory.join(&config.subpath);
+        let git = open_backend(options.git_backend, &subpath)
+            .context("Failed to open the git repository")?;
+        Ok(Releaser {
+            config,
+            subpath,
+            git,
+            options,
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The directory of the package, the hooks run in it
+    pub fn subpath(&self) -> &Path {
+        &self.subpath
+    }
+
+    fn channel(&self) -> eyre::Result<Channel> {
+        let current_branch = self
+            .git
+            .current_branch()
+            .context("Failed to get the current branch")?;
+        channel::channel_for(
+            &self.config.branches,
+            current_branch.as_deref(),
+            self.options.allow.allow_unmatched_stable,
+        )
+    }
+
+    fn read_state<V: Display>(
+        &self,
+        parse: impl Fn(&str) -> eyre::Result<V>,
+    ) -> eyre::Result<(Vec<FileVersion<V>>, Vec<String>)> {
+        info!("Reading the versions of the configured files");
+        let versions = consistency::read_versions_with(&self.subpath, &self.config.files, parse)?;
+        info!("Fetching tags");
+        let tags = self.git.tags().context("Failed to get git tags")?;
+        Ok((versions, tags))
+    }
+
+    fn updates<V: Version>(
+        &self,
+        versions: &[FileVersion<V>],
+        next: &str,
+        tags: &[String],
+        parse: impl Fn(&str) -> eyre::Result<V>,
+    ) -> eyre::Result<Vec<FileUpdate>> {
+        versions
+            .iter()
+            .map(|version| {
+                let file = &version.file;
+                info!("Handling file {}", style(&file.path).bold());
+                let mut version_file = load_versionfile(&version.filepath, file)
+                    .context("Failed to build internal representation of project file")?;
+                info!(
+                    "Parsing {:?} with type {:?}",
+                    version.filepath, file.project_type
+                );
+                let values = file
+                    .values
+                    .iter()
+                    .map(|value| {
+                        Ok(KeyValue {
+                            key: value.key.clone(),
+                            value: render_value(&value.value, next, tags, &parse).wrap_err_with(
+                                || format!("Failed to render {} of {}", value.key, file.path),
+                            )?,
+                        })
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                let content = version_file
+                    .update_values(next, &values)
+                    .context("Failed to update the project file")?;
+                Ok(FileUpdate {
+                    filename: file.path.clone(),
+                    content,
+                    version: next.to_string(),
+                    file: file.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Computes the next release from the commits since the last tag, `None` if there is nothing to release
+    #[instrument(level = "trace", skip(self))]
+    pub fn plan(&self) -> eyre::Result<Option<ReleasePlan>> {
+        match self.config.scheme.calendar_format()? {
+            None => self.plan_with(|raw| raw.parse::<SemanticVersion>()),
+            Some(format) => self.plan_with(|raw| CalendarVersion::parse(raw, &format)),
+        }
+    }
+
+    /// The release for versions of any scheme, the scheme calculates the next version
+    fn plan_with<V: Version>(
+        &self,
+        parse: impl Fn(&str) -> eyre::Result<V>,
+    ) -> eyre::Result<Option<ReleasePlan>> {
+        let channel = self.channel()?;
+        V::check_channel(&channel)?;
+        let version_override = self
+            .options
+            .version_override
+            .as_ref()
+            .map(|version_override| version_override.parse(&parse))
+            .transpose()?;
+        let (versions, all_tags) = self.read_state(&parse)?;
+        consistency::ensure_consistent(&versions)?;
+        let Some(current) = versions.first().map(|version| version.version.clone()) else {
+            info!("There are no files to release");
+            return Ok(None);
+        };
+        debug!("Version: {}", current);
+
+        let tagprefix = &self.config.tagprefix;
+        let last_tag = format!("{}{}", tagprefix, current);
+        if !all_tags.contains(&last_tag) {
+            warn!("Could not find the tag {}", style(&last_tag).bold());
+            warn!("Stopping execution");
+            return Ok(None);
+        }
+        info!(
+            "Found {} as the latest relevant tag",
+            style(&last_tag).bold()
+        );
+
+        info!("Fetching relevant commits");
+        let commits = self
+            .git
+            .commits(Some(&last_tag), "HEAD", Path::new("."))
+            .context("Failed to get git commits")?;
+        debug!("Found {:?} as relevant commits", commits);
+        let commits = git::drop_reverted(commits);
+
+        if commits.is_empty() && version_override.is_none() {
+            info!("No relevant commits found. Not doing anything");
+            return Ok(None);
+        }
+
+        info!("Calculating Bumplevel");
+        let bumplevel = if commits.is_empty() {
+            BumpLevel::None
+        } else {
+            calc_bumplevel(&commits, &self.config.revert_type)
+        };
+        info!("Bumplevel: {:?}", style(&bumplevel).bold());
+        if bumplevel == BumpLevel::None && version_override.is_none() {
+            info!("None of the commits lead to a release. Pass --bump patch to release anyway");
+            return Ok(None);
+        }
+
+        let released = channel::released_versions_with(&all_tags, tagprefix, &parse);
+        let next = channel::release_version(
+            &channel,
+            &current,
+            bumplevel,
+            &released,
+            version_override.as_ref(),
+            self.config.initial_development,
+            self.options.clock.as_ref(),
+        )?
+        .to_string();
+        let updates = self.updates(&versions, &next, &all_tags, &parse)?;
+        // TODO: Maybe make tagging optional?
+        Ok(Some(ReleasePlan {
+            format: PLAN_FORMAT,
+            head: self.git.head()?,
+            changelog: ReleaseNotes::new(
+                &next,
+                &commits,
+                &self.config.revert_type,
+                &self.config.references,
+            )
+            .markdown(),
+            commit_message: format!("{}: Release {}", git::RELEASE_COMMIT_PREFIX, next),
+            tag: Some(format!("{}{}", tagprefix, next)),
+            previous_version: current.to_string(),
+            next_version: next,
+            bumplevel,
+            updates,
+            previous_tag: last_tag,
+        }))
+    }
+
+    /// Computes the commit that aligns the versions of all files, without a bump or a tag.
+    /// `None` if all files have the version already.
+    #[instrument(level = "trace", skip(self))]
+    pub fn plan_sync(&self, target: SyncTarget) -> eyre::Result<Option<ReleasePlan>> {
+        match self.config.scheme.calendar_format()? {
+            None => self.plan_sync_with(target, |raw| raw.parse::<SemanticVersion>()),
+            Some(format) => self.plan_sync_with(target, |raw| CalendarVersion::parse(raw, &format)),
+        }
+    }
+
+    fn plan_sync_with<V: Version>(
+        &self,
+        target: SyncTarget,
+        parse: impl Fn(&str) -> eyre::Result<V>,
+    ) -> eyre::Result<Option<ReleasePlan>> {
+        let (versions, all_tags) = self.read_state(&parse)?;
+        let released = channel::released_versions_with(&all_tags, &self.config.tagprefix, &parse);
+        let version = consistency::sync_version(target, &versions, &released)?;
+        info!("Syncing the versions to {}", style(&version).bold());
+        let updates = consistency::sync_updates(&versions, &version)?;
+        if updates.is_empty() {
+            info!("All files are at {} already", version);
+            return Ok(None);
+        }
+        let previous_version = released
+            .iter()
+            .max()
+            .map_or("0.0.0".to_string(), ToString::to_string);
+        Ok(Some(ReleasePlan {
+            format: PLAN_FORMAT,
+            head: self.git.head()?,
+            changelog: String::new(),
+            commit_message: format!(
+                "{}: Sync versions to {}",
+                git::RELEASE_COMMIT_PREFIX,
+                version
+            ),
+            previous_tag: format!("{}{}", self.config.tagprefix, previous_version),
+            previous_version,
+            next_version: version.to_string(),
+            bumplevel: BumpLevel::None,
+            updates,
+            tag: None,
+        }))
+    }
+
+    fn preflight(&self, plan: &ReleasePlan, dryrun: bool) -> eyre::Result<()> {
+        // the files are read back like the config says, a plan can't bring its own type or plugin
+        for update in &plan.updates {
+            let Some(configured) = self
+                .config
+                .files
+                .iter()
+                .find(|file| file.path == update.filename)
+            else {
+                return Err(eyre::eyre!(
+                    "The plan updates {}, which isn't one of the configured files",
+                    update.filename
+                ))
+                .suggestion("Create the plan again with the config it gets applied with");
+            };
+            if *configured != update.file {
+                return Err(eyre::eyre!(
+                    "The plan handles {} differently than the config, e.g. with another type, key or command",
+                    update.filename
+                ))
+                .suggestion("Create the plan again with the config it gets applied with");
+            }
+        }
+        preflight::run_checks(
+            self.git.as_ref(),
+            &self.config,
+            &self.options.allow,
+            &plan.release_files(&self.subpath),
+            plan.tag.as_deref(),
+            dryrun,
+        )
+    }
+
+    /// The changelog file of the config and its content with the notes of `plan` on top.
+    /// `None` without a changelog in the config or for a plan without a tag, which isn't a release.
+    fn changelog_update(&self, plan: &ReleasePlan) -> eyre::Result<Option<(String, String)>> {
+        let Some(filename) = self
+            .config
+            .changelog
+            .as_ref()
+            .filter(|_| plan.tag.is_some())
+        else {
+            return Ok(None);
+        };
+        let filepath = self.subpath.join(filename);
+        let existing = match fs::read_to_string(&filepath) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            existing => {
+                existing.wrap_err_with(|| format!("Failed to read {}", filepath.display()))?
+            }
+        };
+        Ok(Some((
+            filename.clone(),
+            notes::prepend(&existing, &plan.changelog),
+        )))
+    }
+
+    /// Runs the checks of [Releaser::apply] and reports what it would change, without changing anything.
+    /// Failed pre-flight checks are only logged.
+    #[instrument(level = "trace", skip_all)]
+    pub fn dry_run(&self, plan: &ReleasePlan) -> eyre::Result<DryRunReport> {
+        self.preflight(plan, true)?;
+        transaction::validate(&plan.updates).wrap_err("Failed to validate the updated files")?;
+        info!("Dry run is active, not changing anything");
+        if plan.tag.is_some() {
+            for stage in [
+                Stage::PreRelease,
+                Stage::PostVersionUpdate,
+                Stage::PostCommit,
+                Stage::PostTag,
+            ] {
+                hooks::announce(&self.config.hooks, stage);
+            }
+        }
+        let changelog = self.changelog_update(plan)?;
+        let mut changes: Vec<(&str, &str)> = plan
+            .updates
+            .iter()
+            .map(|update| (update.filename.as_str(), update.content.as_str()))
+            .collect();
+        changes.extend(
+            changelog
+                .iter()
+                .map(|(filename, content)| (filename.as_str(), content.as_str())),
+        );
+        DryRunReport::new(
+            &self.subpath,
+            &changes,
+            &plan.commit_message,
+            plan.tag.as_deref(),
+        )
+    }
+
+    /// Writes the updates, commits them and tags the commit if the plan has a tag.
+    /// The hooks only run for plans with a tag. Refuses plans that were computed at another commit.
+    #[instrument(level = "trace", skip_all)]
+    pub fn apply(&self, plan: &ReleasePlan) -> eyre::Result<()> {
+        let git = self.git.as_ref();
+        let head = git.head().wrap_err("Failed to resolve HEAD")?;
+        if head != plan.head {
+            return Err(eyre::eyre!(
+                "HEAD moved from {} to {} since the plan was made",
+                plan.head,
+                head
+            ))
+            .suggestion("Create a new plan, the commits since then might lead to another release");
+        }
+        self.preflight(plan, false)?;
+        let release = plan.release_context(&self.subpath);
+        let run_hooks = |stage: Stage, release: &ReleaseContext| -> eyre::Result<()> {
+            hooks::run(&self.config.hooks, stage, release)
+        };
+        if let Some(release) = &release {
+            run_hooks(Stage::PreRelease, release)
+                .suggestion("Nothing was changed yet, fix the hook and run the release again")?;
+        }
+        let mut transaction = Transaction::prepare(&self.subpath, &plan.updates)
+            .wrap_err("Failed to validate the updated files")?;
+        let changelog = self.changelog_update(plan)?;
+        if let Some((filename, content)) = &changelog {
+            transaction.add(self.subpath.join(filename), content)?;
+        }
+        transaction.apply()?;
+
+        // what the index held before, so a failed commit doesn't leave the files staged
+        let mut staged: Vec<(PathBuf, Option<IndexEntry>)> = Vec::new();
+        let mut stage = |path: &Path| -> eyre::Result<()> {
+            let entry = git.index_entry(path)?;
+            git.stage(path)?;
+            staged.push((path.to_path_buf(), entry));
+            Ok(())
+        };
+        let committed = (|| -> eyre::Result<String> {
+            let mut hook_files = Vec::new();
+            if let Some(release) = &release {
+                let before = git.changed_files()?;
+                run_hooks(Stage::PostVersionUpdate, release)?;
+                hook_files = git
+                    .changed_files()?
+                    .into_iter()
+                    .filter(|file| !before.contains(file) && file.is_file())
+                    .map(|file| hooks::relative_path(&release.subpath, &file))
+                    .collect();
+            }
+            let filenames = plan
+                .updates
+                .iter()
+                .map(|update| &update.filename)
+                .chain(changelog.iter().map(|(filename, _)| filename));
+            for filename in filenames {
+                info!("Adding {} to the git commit", style(filename).bold());
+                stage(Path::new(filename))
+                    .wrap_err_with(|| format!("Failed to add {} to the git index", filename))?;
+            }
+            for file in hook_files {
+                info!(
+                    "Adding {} changed by the hooks to the git commit",
+                    style(file.display()).bold()
+                );
+                stage(&file).wrap_err_with(|| {
+                    format!("Failed to add {} to the git index", file.display())
+                })?;
+            }
+
+            info!("Doing the git commit");
+            git.commit(&plan.commit_message)
+                .wrap_err("Failed to commit the release")
+        })();
+        let commit = match committed {
+            Ok(commit) => commit,
+            Err(err) => {
+                warn!("Restoring the project files");
+                transaction.rollback();
+                // put back what was staged before, uncommitted changes that were there already stay unstaged
+                for (path, entry) in staged.iter().rev() {
+                    if let Err(err) = git.restore_index_entry(path, entry.as_ref()) {
+                        warn!(
+                            "Failed to restore {} in the git index: {:?}",
+                            path.display(),
+                            err
+                        );
+                    }
+                }
+                return Err(err).suggestion(
+                    "The project files and the git index were restored, changes of the hooks to other files were kept. Fix the reported problem (e.g. a rejecting commit hook) and run the release again",
+                );
+            }
+        };
+
+        if let Some(mut release) = release {
+            release.commit = Some(commit);
+            run_hooks(Stage::PostCommit, &release)
+                .suggestion("The release commit exists already but isn't tagged yet")?;
+            info!(
+                "Tagging the release with tag {}",
+                style(&release.tag).bold()
+            );
+            git.tag(&release.tag)
+                .wrap_err_with(|| format!("Failed to create the tag {}", release.tag))
+                .suggestion("The release commit already exists. Create the tag manually once the problem is fixed")?;
+            run_hooks(Stage::PostTag, &release)
+                .suggestion("The release commit and tag exist already")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use tempfile::TempDir;
+    use toml_edit::{de::from_document, Document};
+
+    use crate::{
+        config::ProjectType,
+        testing::{self, commit, git},
+    };
+
+    use super::*;
+
+    /// A repository with a tagged package.json at 1.0.0
+    fn package() -> TempDir {
+        let dir = testing::repo();
+        let path = dir.path();
+        fs::write(
+            path.join("package.json"),
+            "{\n  \"name\": \"app\",\n  \"version\": \"1.0.0\"\n}\n",
+        )
+        .unwrap();
+        commit(path, "chore: initial");
+        git(path, &["tag", "v1.0.0"]);
+        dir
+    }
+
+    fn releaser(directory: &Path) -> Releaser {
+        releaser_with(directory, ReleaseOptions::default())
+    }
+
+    fn releaser_with(directory: &Path, options: ReleaseOptions) -> Releaser {
+        let config = "subpath = \".\"\ntagprefix = \"v\"\n\n[[files]]\npath = \"package.json\"\nkey = \"version\"\ntype = \"json\"\n";
+        let config: Config = from_document(config.parse::<Document>().unwrap()).unwrap();
+        Releaser::new(config, directory, options).unwrap()
+    }
+
+    #[test]
+    fn plan_then_apply() {
+        let dir = package();
+        let path = dir.path();
+        fs::write(path.join("feature.txt"), "new").unwrap();
+        commit(path, "feat: a feature");
+
+        let releaser = releaser(path);
+        let plan = releaser.plan().unwrap().unwrap();
+        assert_eq!(plan.next_version.to_string(), "1.1.0");
+        assert_eq!(plan.bumplevel, BumpLevel::Minor);
+        assert_eq!(plan.tag.as_deref(), Some("v1.1.0"));
+        // planning doesn't touch anything
+        assert!(fs::read_to_string(path.join("package.json"))
+            .unwrap()
+            .contains("1.0.0"));
+
+        releaser.apply(&plan).unwrap();
+        assert!(fs::read_to_string(path.join("package.json"))
+            .unwrap()
+            .contains("1.1.0"));
+        assert_eq!(git(path, &["tag", "--list", "v1.1.0"]), "v1.1.0");
+        assert_eq!(
+            git(path, &["log", "-1", "--format=%s"]),
+            "[Semantic release]: Release 1.1.0"
+        );
+    }
+
+    #[test]
+    fn plans_survive_the_disk() {
+        let dir = package();
+        let path = dir.path();
+        fs::write(path.join("fix.txt"), "fix").unwrap();
+        commit(path, "fix: a fix");
+
+        let plan = releaser(path).plan().unwrap().unwrap();
+        assert!(plan
+            .changelog
+            .starts_with("## 1.0.1\n\n### Bug fixes\n\n- a fix ("));
+        let file = TempDir::new().unwrap();
+        let file = file.path().join("plan.json");
+        plan.write(&file).unwrap();
+        let read = ReleasePlan::read(&file).unwrap();
+        assert_eq!(read.next_version, plan.next_version);
+        assert_eq!(read.bumplevel, BumpLevel::Patch);
+        assert_eq!(read.updates[0].content, plan.updates[0].content);
+
+        // a commit after planning might change the release
+        fs::write(path.join("feature.txt"), "new").unwrap();
+        commit(path, "feat: a feature");
+        let err = releaser(path).apply(&read).unwrap_err();
+        assert!(err.to_string().starts_with("HEAD moved from"), "{}", err);
+        assert_eq!(git(path, &["tag", "--list", "v1.0.1"]), "");
+    }
+
+    #[test]
+    fn plans_apply_to_another_clone() {
+        let dir = package();
+        let path = dir.path();
+        fs::write(path.join("feature.txt"), "new").unwrap();
+        commit(path, "feat: a feature");
+        let plan = releaser(path).plan().unwrap().unwrap();
+        let file = TempDir::new().unwrap();
+        let file = file.path().join("plan.json");
+        plan.write(&file).unwrap();
+
+        let clone = TempDir::new().unwrap();
+        let clone_path = clone.path();
+        git(
+            path,
+            &["clone", "--quiet", ".", clone_path.to_str().unwrap()],
+        );
+        git(clone_path, &["config", "user.name", "Test"]);
+        git(clone_path, &["config", "user.email", "test@example.com"]);
+        releaser(clone_path)
+            .apply(&ReleasePlan::read(&file).unwrap())
+            .unwrap();
+        assert!(fs::read_to_string(clone_path.join("package.json"))
+            .unwrap()
+            .contains("1.1.0"));
+        assert_eq!(git(clone_path, &["tag", "--list", "v1.1.0"]), "v1.1.0");
+        // the checkout the plan was made in stays as it is
+        assert!(fs::read_to_string(path.join("package.json"))
+            .unwrap()
+            .contains("1.0.0"));
+        assert_eq!(git(path, &["tag", "--list", "v1.1.0"]), "");
+
+        // only the configured files get written
+        let mut plan = plan;
+        plan.updates[0].filename = "other.json".to_string();
+        let err = releaser(path).apply(&plan).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("isn't one of the configured files"),
+            "{}",
+            err
+        );
+        assert!(!path.join("other.json").exists());
+
+        // a configured file with another type would run whatever the plan says
+        let marker = path.join("marker");
+        let script = clone_path.join("plugin.sh");
+        fs::write(&script, format!("#!/bin/sh\ntouch {}\n", marker.display())).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut tampered = ReleasePlan::read(&file).unwrap();
+        tampered.updates[0].file.project_type = ProjectType::Exec;
+        tampered.updates[0].file.command = Some(script.display().to_string());
+        let err = releaser(path).dry_run(&tampered).unwrap_err();
+        assert!(
+            err.to_string().contains("differently than the config"),
+            "{}",
+            err
+        );
+        assert!(releaser(path).apply(&tampered).is_err());
+        assert!(!marker.exists());
+        assert!(fs::read_to_string(path.join("package.json"))
+            .unwrap()
+            .contains("1.0.0"));
+    }
+
+    #[test]
+    fn nothing_to_plan() {
+        let dir = package();
+        let path = dir.path();
+        assert!(releaser(path).plan().unwrap().is_none());
+
+        fs::write(path.join("notes.txt"), "new").unwrap();
+        commit(path, "docs: some notes");
+        assert!(releaser(path).plan().unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_commit_restores_files_and_index() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = package();
+        let path = dir.path();
+        fs::write(path.join("feature.txt"), "new").unwrap();
+        commit(path, "feat: a feature");
+        let hook = path.join(".git/hooks/pre-commit");
+        fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+        // an uncommitted edit that must neither get lost nor staged
+        let edited = "{\n  \"name\": \"renamed\",\n  \"version\": \"1.0.0\"\n}\n";
+        fs::write(path.join("package.json"), edited).unwrap();
+
+        let options = ReleaseOptions {
+            git_backend: BackendKind::Cli,
+            ..ReleaseOptions::default()
+        };
+        let releaser = releaser_with(path, options);
+        let plan = releaser.plan().unwrap().unwrap();
+        assert!(releaser.apply(&plan).is_err());
+
+        assert_eq!(
+            fs::read_to_string(path.join("package.json")).unwrap(),
+            edited
+        );
+        assert_eq!(git(path, &["diff", "--cached", "--name-only"]), "");
+        assert_eq!(git(path, &["tag", "--list", "v1.1.0"]), "");
+    }
+}
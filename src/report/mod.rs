@@ -0,0 +1,185 @@
+use std::{fmt, fs, io, path::Path};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{self, WrapErr};
+use serde::Serialize;
+use similar::TextDiff;
+
+/// How the dry run report gets printed
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum DiffFormat {
+    /// Human readable unified diffs
+    #[default]
+    Unified,
+    /// A single JSON document, e.g. to post it as a pull request comment
+    Json,
+}
+
+/// A git operation the release would perform
+#[derive(Serialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Operation {
+    Stage { path: String },
+    Commit { message: String },
+    Tag { name: String },
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Stage { path } => write!(f, "git add {}", path),
+            Operation::Commit { message } => write!(f, "git commit -m {:?}", message),
+            Operation::Tag { name } => write!(f, "git tag {}", name),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileDiff {
+    pub path: String,
+    pub diff: String,
+}
+
+/// Everything a release would change, printed instead of doing it during a dry run
+#[derive(Serialize, Debug)]
+pub struct DryRunReport {
+    pub files: Vec<FileDiff>,
+    pub commit_message: String,
+    /// Missing if the commit doesn't get tagged, e.g. when syncing versions
+    pub tag: Option<String>,
+    pub operations: Vec<Operation>,
+}
+
+impl DryRunReport {
+    /// `changes` are the paths relative to `subpath` and their new content, a file that doesn't exist yet is created
+    pub fn new(
+        subpath: &Path,
+        changes: &[(&str, &str)],
+        commit_message: &str,
+        tag: Option<&str>,
+    ) -> eyre::Result<Self> {
+        let mut files = Vec::new();
+        for (filename, content) in changes {
+            let filepath = subpath.join(filename);
+            let original = match fs::read_to_string(&filepath) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+                original => {
+                    original.wrap_err_with(|| format!("Failed to read {}", filepath.display()))?
+                }
+            };
+            let diff = TextDiff::from_lines(original.as_str(), *content)
+                .unified_diff()
+                .header(&format!("a/{}", filename), &format!("b/{}", filename))
+                .to_string();
+            files.push(FileDiff {
+                path: filename.to_string(),
+                diff,
+            });
+        }
+        let mut operations: Vec<Operation> = changes
+            .iter()
+            .map(|(filename, _)| Operation::Stage {
+                path: filename.to_string(),
+            })
+            .collect();
+        operations.push(Operation::Commit {
+            message: commit_message.to_string(),
+        });
+        if let Some(tag) = tag {
+            operations.push(Operation::Tag {
+                name: tag.to_string(),
+            });
+        }
+        Ok(DryRunReport {
+            files,
+            commit_message: commit_message.to_string(),
+            tag: tag.map(str::to_string),
+            operations,
+        })
+    }
+
+    pub fn render(&self, format: DiffFormat) -> eyre::Result<String> {
+        match format {
+            DiffFormat::Unified => Ok(self.to_string()),
+            DiffFormat::Json => serde_json::to_string_pretty(self)
+                .map(|json| json + "\n")
+                .wrap_err("Failed to serialize the dry run report"),
+        }
+    }
+}
+
+impl fmt::Display for DryRunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            write!(f, "{}", file.diff)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Commit message: {}", self.commit_message)?;
+        if let Some(tag) = &self.tag {
+            writeln!(f, "Tag: {}", tag)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "Git operations:")?;
+        for (i, operation) in self.operations.iter().enumerate() {
+            writeln!(f, "  {}. {}", i + 1, operation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn report() -> (TempDir, DryRunReport) {
+        let dir = TempDir::new().unwrap();
+        let filepath = dir.path().join("Cargo.toml");
+        fs::write(&filepath, "[package]\nname = \"x\"\nversion = \"1.0.0\"\n").unwrap();
+        let changes = [
+            (
+                "Cargo.toml",
+                "[package]\nname = \"x\"\nversion = \"1.1.0\"\n",
+            ),
+            ("CHANGELOG.md", "# Changelog\n"),
+        ];
+        let report = DryRunReport::new(
+            dir.path(),
+            &changes,
+            "[Semantic release]: Release 1.1.0",
+            Some("v1.1.0"),
+        )
+        .unwrap();
+        (dir, report)
+    }
+
+    #[test]
+    fn unified_report() {
+        let (_dir, report) = report();
+        let rendered = report.render(DiffFormat::Unified).unwrap();
+        assert!(rendered.contains("--- a/Cargo.toml\n+++ b/Cargo.toml\n"));
+        assert!(rendered.contains("-version = \"1.0.0\"\n+version = \"1.1.0\"\n"));
+        // a file that doesn't exist yet is new
+        assert!(
+            rendered
+                .contains("--- a/CHANGELOG.md\n+++ b/CHANGELOG.md\n@@ -0,0 +1 @@\n+# Changelog\n"),
+            "{}",
+            rendered
+        );
+        assert!(rendered.contains(
+            "Git operations:\n  1. git add Cargo.toml\n  2. git add CHANGELOG.md\n  3. git commit -m \"[Semantic release]: Release 1.1.0\"\n  4. git tag v1.1.0\n"
+        ));
+    }
+
+    #[test]
+    fn json_report() {
+        let (_dir, report) = report();
+        let rendered = report.render(DiffFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(json["tag"], "v1.1.0");
+        assert_eq!(json["files"][0]["path"], "Cargo.toml");
+        assert_eq!(json["operations"][0]["type"], "stage");
+        assert_eq!(json["operations"][3]["name"], "v1.1.0");
+    }
+}
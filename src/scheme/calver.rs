@@ -0,0 +1,304 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use color_eyre::{eyre, Help};
+use console::style;
+use tracing::{info, instrument};
+
+use crate::{
+    config::{Channel, MaintenanceRange},
+    git::BumpLevel,
+};
+
+use super::{Clock, Date, Version};
+
+/// One part of a calendar version, named like on calver.org
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    /// `YYYY`, e.g. 2026
+    FullYear,
+    /// `YY`, the year since 2000 without padding, e.g. 6 or 26
+    ShortYear,
+    /// `0Y`, the year since 2000 padded to two digits, e.g. 06
+    PaddedYear,
+    /// `MM`, e.g. 1 or 10
+    Month,
+    /// `0M`, e.g. 01 or 10
+    PaddedMonth,
+    /// `DD`, e.g. 5 or 18
+    Day,
+    /// `0D`, e.g. 05 or 18
+    PaddedDay,
+    /// `MICRO`, counts the releases with the same date parts starting at 0
+    Micro,
+}
+
+const PARTS: [(&str, Part); 8] = [
+    ("YYYY", Part::FullYear),
+    ("YY", Part::ShortYear),
+    ("0Y", Part::PaddedYear),
+    ("MM", Part::Month),
+    ("0M", Part::PaddedMonth),
+    ("DD", Part::Day),
+    ("0D", Part::PaddedDay),
+    ("MICRO", Part::Micro),
+];
+
+impl Part {
+    fn name(self) -> &'static str {
+        PARTS
+            .iter()
+            .find(|(_, part)| *part == self)
+            .map(|(name, _)| *name)
+            .expect("every part has a name")
+    }
+
+    /// The value of the part on `date`, `None` for `MICRO`
+    fn value(self, date: Date) -> Option<u32> {
+        match self {
+            Part::FullYear => Some(date.year),
+            Part::ShortYear | Part::PaddedYear => Some(date.year.saturating_sub(2000)),
+            Part::Month | Part::PaddedMonth => Some(date.month),
+            Part::Day | Part::PaddedDay => Some(date.day),
+            Part::Micro => None,
+        }
+    }
+
+    fn is_valid(self, value: u32) -> bool {
+        match self {
+            Part::Month | Part::PaddedMonth => (1..=12).contains(&value),
+            Part::Day | Part::PaddedDay => (1..=31).contains(&value),
+            _ => true,
+        }
+    }
+
+    fn is_padded(self) -> bool {
+        matches!(self, Part::PaddedYear | Part::PaddedMonth | Part::PaddedDay)
+    }
+}
+
+/// The parts of a calendar version separated by dots, e.g. `YYYY.0M.MICRO` or `YY.MM.DD`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarFormat {
+    parts: Vec<Part>,
+}
+
+impl FromStr for CalendarFormat {
+    type Err = eyre::Report;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        let suggestion = "Use parts like YYYY, YY, 0Y, MM, 0M, DD, 0D and MICRO separated by dots, e.g. YYYY.0M.MICRO";
+        let parts = format
+            .split('.')
+            .map(|name| {
+                PARTS
+                    .iter()
+                    .find(|(part, _)| *part == name)
+                    .map(|(_, part)| *part)
+                    .ok_or_else(|| eyre::eyre!("{} is not a part of a calendar version", name))
+                    .suggestion(suggestion)
+            })
+            .collect::<eyre::Result<Vec<Part>>>()?;
+        if !matches!(
+            parts.first(),
+            Some(Part::FullYear | Part::ShortYear | Part::PaddedYear)
+        ) || parts.len() < 2
+        {
+            return Err(eyre::eyre!(
+                "The calendar version format {} has to start with the year and have at least two parts",
+                format
+            ))
+            .suggestion(suggestion);
+        }
+        if parts[..parts.len() - 1].contains(&Part::Micro) {
+            return Err(eyre::eyre!(
+                "MICRO has to be the last part of the calendar version format {}",
+                format
+            ))
+            .suggestion(suggestion);
+        }
+        Ok(CalendarFormat { parts })
+    }
+}
+
+impl fmt::Display for CalendarFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.parts.iter().map(|part| part.name()).collect();
+        write!(f, "{}", names.join("."))
+    }
+}
+
+/// A version like `2026.10.0` that follows a [CalendarFormat]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarVersion {
+    format: CalendarFormat,
+    values: Vec<u32>,
+}
+
+impl CalendarVersion {
+    /// Parses `version`, padding is optional but the number of parts has to match the format
+    pub fn parse(version: &str, format: &CalendarFormat) -> eyre::Result<Self> {
+        let values: Vec<&str> = version.split('.').collect();
+        if values.len() != format.parts.len() {
+            eyre::bail!(
+                "{} doesn't follow the calendar version format {}",
+                version,
+                format
+            );
+        }
+        let values = values
+            .iter()
+            .zip(&format.parts)
+            .map(|(value, part)| {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|number| part.is_valid(*number))
+                    .ok_or_else(|| {
+                        eyre::eyre!("{} is not a valid {} in {}", value, part.name(), version)
+                    })
+            })
+            .collect::<eyre::Result<Vec<u32>>>()?;
+        Ok(CalendarVersion {
+            format: format.clone(),
+            values,
+        })
+    }
+}
+
+impl fmt::Display for CalendarVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (value, part)) in self.values.iter().zip(&self.format.parts).enumerate() {
+            if index > 0 {
+                write!(f, ".")?;
+            }
+            if part.is_padded() {
+                write!(f, "{:02}", value)?;
+            } else {
+                write!(f, "{}", value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Ord for CalendarVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.values.cmp(&other.values)
+    }
+}
+
+impl PartialOrd for CalendarVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Version for CalendarVersion {
+    /// The date parts of today, `MICRO` counts up if they didn't change since this version and starts at 0 otherwise.
+    /// The bumplevel only decides whether there is a release, [Version::check_channel] allows the stable channel only.
+    #[instrument(level = "trace", name = "CalendarVersion::next")]
+    fn next(
+        &self,
+        _channel: &Channel,
+        _bumplevel: BumpLevel,
+        _initial_development: bool,
+        clock: &dyn Clock,
+    ) -> eyre::Result<Self> {
+        let today = clock.today();
+        let parts = &self.format.parts;
+        let mut values: Vec<u32> = parts.iter().filter_map(|part| part.value(today)).collect();
+        let dates = values.len();
+        match values[..].cmp(&self.values[..dates]) {
+            Ordering::Less => {
+                return Err(eyre::eyre!(
+                    "{} is older than the last release {}",
+                    today,
+                    self
+                ))
+                .suggestion("Check the clock of this machine and SOURCE_DATE_EPOCH");
+            }
+            Ordering::Equal => {
+                let Some(micro) = self.values.get(dates) else {
+                    return Err(eyre::eyre!(
+                        "{} was released on {} already, there is no MICRO part to count up",
+                        self,
+                        today
+                    ))
+                    .suggestion("Add MICRO to the end of the format or release again tomorrow");
+                };
+                values.push(micro + 1);
+            }
+            Ordering::Greater => {
+                if parts.last() == Some(&Part::Micro) {
+                    values.push(0);
+                }
+            }
+        }
+        let next = CalendarVersion {
+            format: self.format.clone(),
+            values,
+        };
+        info!("Next version: {}", style(&next).bold());
+        Ok(next)
+    }
+
+    fn check_channel(channel: &Channel) -> eyre::Result<()> {
+        if *channel != Channel::Stable {
+            return Err(eyre::eyre!(
+                "Calendar versions can only be released on the stable channel, not {:?}",
+                channel
+            ))
+            .suggestion("Release from a branch without a prerelease or maintenance channel");
+        }
+        Ok(())
+    }
+
+    /// Calendar versions have no maintenance lines, see [Version::check_channel]
+    fn in_range(&self, _range: &MaintenanceRange) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheme::FixedClock;
+
+    fn version(version: &str, format: &str) -> CalendarVersion {
+        CalendarVersion::parse(version, &format.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        let format: CalendarFormat = "YYYY.0M.MICRO".parse().unwrap();
+        assert_eq!(format.to_string(), "YYYY.0M.MICRO");
+        assert_eq!(
+            version("2026.1.3", "YYYY.0M.MICRO").to_string(),
+            "2026.01.3"
+        );
+        assert_eq!(version("26.10.18", "YY.MM.DD").to_string(), "26.10.18");
+        assert!(version("2026.9.0", "YYYY.0M.MICRO") < version("2026.10.0", "YYYY.0M.MICRO"));
+
+        assert!("YYYY.MICRO.MM".parse::<CalendarFormat>().is_err());
+        assert!("MM.YYYY".parse::<CalendarFormat>().is_err());
+        assert!("YYYY.Q".parse::<CalendarFormat>().is_err());
+        let format = "YYYY.0M.MICRO".parse().unwrap();
+        assert!(CalendarVersion::parse("2026.13.0", &format).is_err());
+        assert!(CalendarVersion::parse("1.2.3.4", &format).is_err());
+    }
+
+    #[test]
+    fn next_versions() {
+        let clock = FixedClock(Date::new(2026, 10, 18));
+        let next = |current: &str, format: &str| {
+            version(current, format)
+                .next(&Channel::Stable, BumpLevel::Patch, false, &clock)
+                .map(|next| next.to_string())
+        };
+        assert_eq!(next("2026.09.4", "YYYY.0M.MICRO").unwrap(), "2026.10.0");
+        assert_eq!(next("2026.10.4", "YYYY.0M.MICRO").unwrap(), "2026.10.5");
+        assert_eq!(next("26.10.17", "YY.MM.DD").unwrap(), "26.10.18");
+        assert!(next("26.10.18", "YY.MM.DD").is_err());
+        assert!(next("2027.01.0", "YYYY.0M.MICRO").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+pub mod calver;
+
+use std::{
+    env, fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre;
+
+use crate::{
+    config::{Channel, MaintenanceRange},
+    git::BumpLevel,
+};
+
+pub use self::calver::{CalendarFormat, CalendarVersion};
+
+/// A way to number the versions of a package.
+/// The commits decide whether there is a release at all, the scheme decides its version.
+pub trait Version: fmt::Display + fmt::Debug + Clone + Ord {
+    /// The version of a release after this one on `channel`, `bumplevel` is what the commits lead to.
+    /// With `initial_development` the bumplevel is lowered while [Version::is_initial_development] holds.
+    fn next(
+        &self,
+        channel: &Channel,
+        bumplevel: BumpLevel,
+        initial_development: bool,
+        clock: &dyn Clock,
+    ) -> eyre::Result<Self>;
+
+    /// Fails for a channel the scheme can't release on
+    fn check_channel(_channel: &Channel) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    /// Whether this version only leads up to a release, e.g. `1.3.0-beta.1`
+    fn is_prerelease(&self) -> bool {
+        false
+    }
+
+    /// Whether this version is from the initial development, e.g. `0.4.2`
+    fn is_initial_development(&self) -> bool {
+        false
+    }
+
+    /// Whether this version belongs to the maintenance `range`
+    fn in_range(&self, range: &MaintenanceRange) -> bool;
+}
+
+/// A day in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn new(year: u32, month: u32, day: u32) -> Self {
+        Date { year, month, day }
+    }
+
+    /// The day `seconds` after the unix epoch
+    pub fn from_timestamp(seconds: u64) -> Self {
+        // days to civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = seconds / 86_400 + 719_468;
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+        Date::new(year as u32, month as u32, day as u32)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Where the date of a release comes from
+pub trait Clock: fmt::Debug + Send + Sync {
+    fn today(&self) -> Date;
+}
+
+/// The current day in UTC, or the day of `SOURCE_DATE_EPOCH` if it is set for reproducible releases
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> Date {
+        let seconds = env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default()
+            });
+        Date::from_timestamp(seconds)
+    }
+}
+
+/// Always the same day, e.g. for tests
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub Date);
+
+impl Clock for FixedClock {
+    fn today(&self) -> Date {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_from_timestamps() {
+        assert_eq!(Date::from_timestamp(0), Date::new(1970, 1, 1));
+        assert_eq!(Date::from_timestamp(951_782_400), Date::new(2000, 2, 29));
+        assert_eq!(Date::from_timestamp(1_792_281_599), Date::new(2026, 10, 17));
+        assert_eq!(Date::new(2026, 1, 5).to_string(), "2026-01-05");
+    }
+}
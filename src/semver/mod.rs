@@ -1,21 +1,83 @@
-use std::fmt;
+use std::{cmp::Ordering, fmt, str::FromStr};
 
-use color_eyre::eyre;
+use color_eyre::eyre::{self, WrapErr};
 use console::style;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tracing::{debug, info, instrument, trace};
 
-use crate::git::BumpLevel;
+use crate::{
+    config::{Channel, MaintenanceRange},
+    git::BumpLevel,
+    scheme::{Clock, Version},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prerelease {
+    identifier: String,
+    number: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SemanticVersion {
     major: usize,
     minor: usize,
     patch: usize,
+    prerelease: Option<Prerelease>,
 }
 
 impl fmt::Display for SemanticVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(prerelease) = &self.prerelease {
+            write!(f, "-{}.{}", prerelease.identifier, prerelease.number)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for SemanticVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let core =
+            (self.major, self.minor, self.patch).cmp(&(other.major, other.minor, other.patch));
+        core.then_with(|| match (&self.prerelease, &other.prerelease) {
+            (None, None) => Ordering::Equal,
+            // a pre-release comes before the release it leads up to
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(own), Some(other)) => own
+                .identifier
+                .cmp(&other.identifier)
+                .then(own.number.cmp(&other.number)),
+        })
+    }
+}
+
+impl PartialOrd for SemanticVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for SemanticVersion {
+    type Err = eyre::Report;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let mut semver = SemanticVersion::new();
+        semver.set_version(version)?;
+        Ok(semver)
+    }
+}
+
+impl Serialize for SemanticVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SemanticVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(de::Error::custom)
     }
 }
 
@@ -25,11 +87,17 @@ impl SemanticVersion {
             major: 0,
             minor: 0,
             patch: 0,
+            prerelease: None,
         }
     }
+
     #[instrument(level = "trace", name = "SemanticVersion::set_version")]
     pub fn set_version(&mut self, version_str: &str) -> eyre::Result<()> {
         debug!("Trying to parse: {}", version_str);
+        let (version_str, prerelease) = match version_str.split_once('-') {
+            Some((version, prerelease)) => (version, Some(prerelease)),
+            None => (version_str, None),
+        };
         let split = version_str.split('.');
         if split.clone().count() < 3 {
             eyre::bail!(
@@ -38,10 +106,14 @@ impl SemanticVersion {
         }
         let mut too_many_parts = false;
         for (i, el) in split.enumerate() {
+            let parse = || {
+                el.parse::<usize>()
+                    .wrap_err_with(|| format!("{} is not a valid version number", el))
+            };
             match i {
-                0 => self.major = el.parse::<usize>().unwrap(),
-                1 => self.minor = el.parse::<usize>().unwrap(),
-                2 => self.patch = el.parse::<usize>().unwrap(),
+                0 => self.major = parse()?,
+                1 => self.minor = parse()?,
+                2 => self.patch = parse()?,
                 _ => too_many_parts = true,
             }
         }
@@ -50,12 +122,76 @@ impl SemanticVersion {
                 "Failed to split the given string into exactly three parts. Found too many parts",
             );
         }
+        self.prerelease = match prerelease {
+            Some(prerelease) => {
+                let (identifier, number) = prerelease.rsplit_once('.').ok_or_else(|| {
+                    eyre::eyre!(
+                        "Pre-release {} doesn't follow the <identifier>.<number> format",
+                        prerelease
+                    )
+                })?;
+                let number = number
+                    .parse::<usize>()
+                    .wrap_err_with(|| format!("{} is not a valid pre-release number", number))?;
+                Some(Prerelease {
+                    identifier: identifier.to_string(),
+                    number,
+                })
+            }
+            None => None,
+        };
         Ok(())
     }
 
+    /// The bumplevel it took to reach this version
+    fn release_level(&self) -> BumpLevel {
+        if self.patch > 0 {
+            BumpLevel::Patch
+        } else if self.minor > 0 {
+            BumpLevel::Minor
+        } else {
+            BumpLevel::Major
+        }
+    }
+
+    /// The bumplevel to use during initial development, while the major version is 0.
+    /// A breaking change bumps the minor and a feature the patch version then, everything else stays the same.
+    fn initial_development_level(&self, bumplevel: BumpLevel) -> BumpLevel {
+        if self.major != 0 {
+            return bumplevel;
+        }
+        match bumplevel {
+            BumpLevel::Major => BumpLevel::Minor,
+            BumpLevel::Minor => BumpLevel::Patch,
+            other => other,
+        }
+    }
+
+    /// Bumps by `bumplevel`, with `initial_development` the level is lowered while the major version is 0
     #[instrument(level = "trace", name = "SemanticVersion::bump")]
-    pub fn bump(&mut self, bumplevel: BumpLevel) {
+    pub fn bump(&mut self, bumplevel: BumpLevel, initial_development: bool) {
         debug!("bumping version: {}", self);
+        let bumplevel = if initial_development {
+            let lowered = self.initial_development_level(bumplevel);
+            if lowered != bumplevel {
+                info!(
+                    "{} is in initial development, bumping by {:?} instead of {:?}",
+                    self, lowered, bumplevel
+                );
+            }
+            lowered
+        } else {
+            bumplevel
+        };
+        if self.prerelease.is_some() && bumplevel != BumpLevel::None {
+            // the pre-release already leads up to a bump, only bump again if that isn't enough
+            self.prerelease = None;
+            if bumplevel <= self.release_level() {
+                trace!("Releasing the pre-release version");
+                info!("Next version: {}", style(&self).bold());
+                return;
+            }
+        }
         match bumplevel {
             BumpLevel::Patch => {
                 trace!("Patch level bump");
@@ -76,7 +212,75 @@ impl SemanticVersion {
                 trace!("No bump happening");
             }
         };
-        info!("Next version: {}", style(self).bold());
+        info!("Next version: {}", style(&self).bold());
+    }
+
+    /// Bumps to the next pre-release with the given identifier, e.g. `1.3.0-beta.2` after `1.3.0-beta.1`.
+    /// `initial_development` works like in [SemanticVersion::bump].
+    #[instrument(level = "trace", name = "SemanticVersion::bump_prerelease")]
+    pub fn bump_prerelease(
+        &mut self,
+        bumplevel: BumpLevel,
+        identifier: &str,
+        initial_development: bool,
+    ) {
+        if bumplevel == BumpLevel::None {
+            trace!("No bump happening");
+            return;
+        }
+        let previous_number = self
+            .prerelease
+            .as_ref()
+            .filter(|prerelease| prerelease.identifier == identifier)
+            .map(|prerelease| prerelease.number);
+        let previous_core = (self.major, self.minor, self.patch);
+        let was_prerelease = self.prerelease.is_some();
+        self.bump(bumplevel, initial_development);
+        let number = match previous_number {
+            Some(number)
+                if was_prerelease && previous_core == (self.major, self.minor, self.patch) =>
+            {
+                number + 1
+            }
+            _ => 1,
+        };
+        self.prerelease = Some(Prerelease {
+            identifier: identifier.to_string(),
+            number,
+        });
+        info!("Next pre-release version: {}", style(&self).bold());
+    }
+}
+
+impl Version for SemanticVersion {
+    /// Bumps by `bumplevel`, or to the next pre-release of the channel. Semantic versions don't depend on the date.
+    fn next(
+        &self,
+        channel: &Channel,
+        bumplevel: BumpLevel,
+        initial_development: bool,
+        _clock: &dyn Clock,
+    ) -> eyre::Result<Self> {
+        let mut next = self.clone();
+        match channel {
+            Channel::Stable | Channel::Maintenance(_) => next.bump(bumplevel, initial_development),
+            Channel::Prerelease(identifier) => {
+                next.bump_prerelease(bumplevel, identifier, initial_development)
+            }
+        }
+        Ok(next)
+    }
+
+    fn is_prerelease(&self) -> bool {
+        self.prerelease.is_some()
+    }
+
+    fn is_initial_development(&self) -> bool {
+        self.major == 0
+    }
+
+    fn in_range(&self, range: &MaintenanceRange) -> bool {
+        self.major == range.major && range.minor.is_none_or(|minor| self.minor == minor)
     }
 }
 
@@ -90,13 +294,77 @@ impl Default for SemanticVersion {
 mod tests {
     use crate::semver::*;
 
+    fn version(version: &str) -> SemanticVersion {
+        let mut semver = SemanticVersion::new();
+        semver.set_version(version).unwrap();
+        semver
+    }
+
     #[test]
     fn to_string() {
         let n = SemanticVersion {
             major: 1,
             minor: 2,
             patch: 3,
+            prerelease: None,
         };
         assert_eq!(n.to_string(), "1.2.3");
+        assert_eq!(version("1.2.3-beta.4").to_string(), "1.2.3-beta.4");
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(version("1.2.3") < version("1.10.0"));
+        assert!(version("1.3.0-beta.1") < version("1.3.0"));
+        assert!(version("1.3.0-beta.2") < version("1.3.0-beta.10"));
+        assert!(version("1.2.3") < version("1.3.0-beta.1"));
+    }
+
+    #[test]
+    fn invalid_versions() {
+        let mut semver = SemanticVersion::new();
+        assert!(semver.set_version("1.2").is_err());
+        assert!(semver.set_version("1.2.x").is_err());
+        assert!(semver.set_version("1.2.3-beta").is_err());
+    }
+
+    #[test]
+    fn release_prerelease() {
+        let mut semver = version("1.3.0-beta.2");
+        semver.bump(BumpLevel::Minor, false);
+        assert_eq!(semver.to_string(), "1.3.0");
+        let mut semver = version("1.3.0-beta.2");
+        semver.bump(BumpLevel::Major, false);
+        assert_eq!(semver.to_string(), "2.0.0");
+    }
+
+    #[test]
+    fn initial_development_bumps() {
+        let mut semver = version("0.4.2");
+        semver.bump(BumpLevel::Major, true);
+        assert_eq!(semver.to_string(), "0.5.0");
+        semver.bump(BumpLevel::Minor, true);
+        assert_eq!(semver.to_string(), "0.5.1");
+        semver.bump(BumpLevel::Patch, true);
+        assert_eq!(semver.to_string(), "0.5.2");
+        semver.bump_prerelease(BumpLevel::Major, "beta", true);
+        assert_eq!(semver.to_string(), "0.6.0-beta.1");
+        // stable versions aren't affected
+        let mut semver = version("1.2.3");
+        semver.bump(BumpLevel::Major, true);
+        assert_eq!(semver.to_string(), "2.0.0");
+    }
+
+    #[test]
+    fn prerelease_bumps() {
+        let mut semver = version("1.2.3");
+        semver.bump_prerelease(BumpLevel::Minor, "beta", false);
+        assert_eq!(semver.to_string(), "1.3.0-beta.1");
+        semver.bump_prerelease(BumpLevel::Patch, "beta", false);
+        assert_eq!(semver.to_string(), "1.3.0-beta.2");
+        semver.bump_prerelease(BumpLevel::Minor, "rc", false);
+        assert_eq!(semver.to_string(), "1.3.0-rc.1");
+        semver.bump_prerelease(BumpLevel::Major, "rc", false);
+        assert_eq!(semver.to_string(), "2.0.0-rc.1");
     }
 }
@@ -0,0 +1,40 @@
+//! Git repositories for the unit tests, like the fixtures in `tests/common` for the integration tests
+
+use std::{path::Path, process::Command};
+
+use tempfile::TempDir;
+
+/// Runs git in `directory` and returns its output without the trailing newline
+pub fn git(directory: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(directory)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string()
+}
+
+/// An empty repository on the branch main with an identity to commit with
+pub fn repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path();
+    git(path, &["init", "--quiet", "--initial-branch=main"]);
+    git(path, &["config", "user.name", "Test"]);
+    git(path, &["config", "user.email", "test@example.com"]);
+    git(path, &["config", "commit.gpgsign", "false"]);
+    dir
+}
+
+/// Commits every change in the repository at `directory`
+pub fn commit(directory: &Path, message: &str) {
+    git(directory, &["add", "--all"]);
+    git(directory, &["commit", "--quiet", "-m", message]);
+}
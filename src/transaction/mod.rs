@@ -0,0 +1,370 @@
+use std::{
+    fs::{self, Permissions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{self, ContextCompat, WrapErr};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tracing::{debug, error, info, instrument};
+
+use crate::{
+    config::ProjectFile,
+    project::{load_versionfile, parse_versionfile},
+};
+
+/// The new content of a project file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileUpdate {
+    /// Relative to the subpath, so a plan can be applied in another checkout
+    pub filename: String,
+    pub content: String,
+    /// The version the content has to contain
+    pub version: String,
+    pub file: ProjectFile,
+}
+
+impl FileUpdate {
+    /// Where the file is in the package at `subpath`
+    pub fn filepath(&self, subpath: &Path) -> PathBuf {
+        subpath.join(&self.filename)
+    }
+}
+
+/// Content waiting in a temporary file next to the file it replaces
+struct Prepared {
+    filepath: PathBuf,
+    temp: NamedTempFile,
+}
+
+/// The state of a file before it got replaced
+struct Original {
+    filepath: PathBuf,
+    /// `None` if the file didn't exist, a rollback removes it then
+    content: Option<Vec<u8>>,
+    permissions: Permissions,
+}
+
+/// Replaces all project files at once or not at all.
+/// Every update gets written to a temporary file in the same directory and parsed again before any file is touched,
+/// the files are then moved into place with a rename which keeps them intact if the process dies midway.
+pub struct Transaction {
+    prepared: Vec<Prepared>,
+    originals: Vec<Original>,
+}
+
+fn temp_file_next_to(filepath: &Path) -> eyre::Result<NamedTempFile> {
+    let directory = filepath
+        .parent()
+        .wrap_err_with(|| format!("{} has no parent directory", filepath.display()))?;
+    NamedTempFile::new_in(directory).wrap_err_with(|| {
+        format!(
+            "Failed to create a temporary file in {}, maybe the user is lacking the necessary permission",
+            directory.display()
+        )
+    })
+}
+
+/// Atomically replaces the file at `filepath`
+fn replace(filepath: &Path, content: &[u8], permissions: &Permissions) -> eyre::Result<()> {
+    let mut temp = temp_file_next_to(filepath)?;
+    temp.write_all(content)
+        .wrap_err("Failed to write to temporary file")?;
+    persist(temp, filepath, permissions)
+}
+
+/// Atomically writes `content` to `filepath`, a file that exists already keeps its permissions
+pub fn write(filepath: &Path, content: &[u8]) -> eyre::Result<()> {
+    // an empty file gets the default permissions, the temporary file would only be readable by the user
+    let permissions = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(filepath)
+        .and_then(|file| file.metadata())
+        .wrap_err_with(|| format!("Failed to open {}", filepath.display()))?
+        .permissions();
+    replace(filepath, content, &permissions)
+}
+
+/// The content and permissions of the file at `filepath`, without content if it doesn't exist.
+/// A new file gets the default permissions like in [write].
+fn current_state(filepath: &Path) -> io::Result<(Option<Vec<u8>>, Permissions)> {
+    let content = match fs::read(filepath) {
+        Ok(content) => Some(content),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    let permissions = match content {
+        Some(_) => fs::metadata(filepath)?.permissions(),
+        None => {
+            let file = fs::File::create_new(filepath)?;
+            let permissions = file.metadata()?.permissions();
+            fs::remove_file(filepath)?;
+            permissions
+        }
+    };
+    Ok((content, permissions))
+}
+
+fn persist(temp: NamedTempFile, filepath: &Path, permissions: &Permissions) -> eyre::Result<()> {
+    fs::set_permissions(temp.path(), permissions.clone())
+        .wrap_err("Failed to copy the permissions to the temporary file")?;
+    debug!("Moving temporary file {:?} to {:?}", temp.path(), filepath);
+    temp.persist(filepath)
+        .wrap_err_with(|| format!("Failed to move the new content to {}", filepath.display()))?;
+    Ok(())
+}
+
+/// Makes sure the update could be parsed again and has the version it is meant to have
+fn check_version(update: &FileUpdate, version: eyre::Result<String>) -> eyre::Result<()> {
+    let version = version
+        .wrap_err_with(|| format!("The updated {} can't be parsed again", update.filename))?;
+    if version != update.version {
+        eyre::bail!(
+            "The updated {} contains the version {} instead of {}",
+            update.filename,
+            version,
+            update.version
+        );
+    }
+    Ok(())
+}
+
+/// Does the checks of [Transaction::prepare] in memory, without writing anything.
+/// Files of plugins can't be checked like that, they are only checked when the release is applied.
+#[instrument(level = "trace", skip(updates))]
+pub fn validate(updates: &[FileUpdate]) -> eyre::Result<()> {
+    for update in updates {
+        let version = match parse_versionfile(&update.content, &update.file) {
+            Ok(Some(version_file)) => version_file.read_version(),
+            Ok(None) => {
+                debug!(
+                    "{} is handled by a plugin, it gets checked when the release is applied",
+                    update.filename
+                );
+                continue;
+            }
+            Err(err) => Err(err),
+        };
+        check_version(update, version)?;
+    }
+    info!("Validated the updates of {} file(s)", updates.len());
+    Ok(())
+}
+
+impl Transaction {
+    /// Writes every update next to its target and checks that it can be read back with the new version.
+    /// The files are looked up in `subpath`, nothing in the repository is changed yet.
+    #[instrument(level = "trace", skip(updates))]
+    pub fn prepare(subpath: &Path, updates: &[FileUpdate]) -> eyre::Result<Transaction> {
+        let mut prepared = Vec::new();
+        for update in updates {
+            let filepath = update.filepath(subpath);
+            let mut temp = temp_file_next_to(&filepath)?;
+            temp.write_all(update.content.as_bytes())
+                .wrap_err("Failed to write to temporary file")?;
+            temp.flush()?;
+            let version = load_versionfile(temp.path(), &update.file)
+                .and_then(|version_file| version_file.read_version());
+            check_version(update, version)?;
+            prepared.push(Prepared { filepath, temp });
+        }
+        info!("Validated the updates of {} file(s)", prepared.len());
+        Ok(Transaction {
+            prepared,
+            originals: Vec::new(),
+        })
+    }
+
+    /// Adds a file without a version to check, e.g. the changelog. It is created if it doesn't exist yet.
+    #[instrument(level = "trace", skip(self, content))]
+    pub fn add(&mut self, filepath: PathBuf, content: &str) -> eyre::Result<()> {
+        let mut temp = temp_file_next_to(&filepath)?;
+        temp.write_all(content.as_bytes())
+            .wrap_err("Failed to write to temporary file")?;
+        temp.flush()?;
+        self.prepared.push(Prepared { filepath, temp });
+        Ok(())
+    }
+
+    /// Moves every prepared file into place, the ones already replaced are restored if one of them fails
+    #[instrument(level = "trace", skip(self))]
+    pub fn apply(&mut self) -> eyre::Result<()> {
+        for prepared in std::mem::take(&mut self.prepared) {
+            let filepath = prepared.filepath;
+            let result = current_state(&filepath)
+                .wrap_err_with(|| format!("Failed to read {}", filepath.display()))
+                .and_then(|(content, permissions)| {
+                    persist(prepared.temp, &filepath, &permissions)?;
+                    Ok(Original {
+                        filepath: filepath.clone(),
+                        content,
+                        permissions,
+                    })
+                });
+            match result {
+                Ok(original) => self.originals.push(original),
+                Err(err) => {
+                    self.rollback();
+                    return Err(err);
+                }
+            }
+            info!("Successfully updated {}", filepath.display());
+        }
+        Ok(())
+    }
+
+    /// Puts back the original content of every file that was replaced.
+    /// Failures are only logged, the error that led to the rollback is the one worth reporting.
+    #[instrument(level = "trace", skip(self))]
+    pub fn rollback(&mut self) {
+        for original in self.originals.drain(..).rev() {
+            info!("Restoring {}", original.filepath.display());
+            let restored = match &original.content {
+                Some(content) => replace(&original.filepath, content, &original.permissions),
+                None => fs::remove_file(&original.filepath)
+                    .wrap_err_with(|| format!("Failed to remove {}", original.filepath.display())),
+            };
+            if let Err(err) = restored {
+                error!(
+                    "Failed to restore {}: {:?}",
+                    original.filepath.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use crate::config::ProjectType;
+
+    use super::*;
+
+    fn update(filename: &str, content: &str, version: &str) -> FileUpdate {
+        FileUpdate {
+            filename: filename.to_string(),
+            content: content.to_string(),
+            version: version.to_string(),
+            file: ProjectFile {
+                path: filename.to_string(),
+                key: "package.version".to_string(),
+                project_type: ProjectType::Toml,
+                command: None,
+                values: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn invalid_update_changes_nothing() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.toml"), "old").unwrap();
+        fs::write(dir.path().join("b.toml"), "old").unwrap();
+        let updates = vec![
+            update("a.toml", "[package]\nversion = \"1.1.0\"", "1.1.0"),
+            update("b.toml", "[package\nversion = \"1.1.0\"", "1.1.0"),
+        ];
+        assert!(Transaction::prepare(dir.path(), &updates).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.toml")).unwrap(),
+            "old"
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn validate_in_memory() {
+        let dir = TempDir::new().unwrap();
+        let valid = update("a.toml", "[package]\nversion = \"1.1.0\"", "1.1.0");
+        validate(std::slice::from_ref(&valid)).unwrap();
+        let invalid = update("b.toml", "[package\nversion = \"1.1.0\"", "1.1.0");
+        assert!(validate(&[valid.clone(), invalid]).is_err());
+        let other = update("c.toml", "[package]\nversion = \"1.0.0\"", "1.1.0");
+        let err = validate(&[other]).unwrap_err();
+        assert!(err.to_string().contains("instead of 1.1.0"), "{}", err);
+        // the files don't even exist
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn atomic_write() {
+        let dir = TempDir::new().unwrap();
+        let created = dir.path().join("CHANGELOG.md");
+        write(&created, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&created).unwrap(), "new");
+
+        let existing = dir.path().join("existing.md");
+        fs::write(&existing, "old").unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&existing, Permissions::from_mode(0o640)).unwrap();
+        write(&existing, b"replaced").unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "replaced");
+        #[cfg(unix)]
+        {
+            // a new file gets the same permissions as one written directly
+            fs::write(dir.path().join("direct.md"), "direct").unwrap();
+            let mode = |name: &str| {
+                fs::metadata(dir.path().join(name))
+                    .unwrap()
+                    .permissions()
+                    .mode()
+            };
+            assert_eq!(mode("CHANGELOG.md"), mode("direct.md"));
+            assert_eq!(mode("existing.md") & 0o777, 0o640);
+        }
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn apply_and_rollback() {
+        let dir = TempDir::new().unwrap();
+        let filepath = dir.path().join("a.toml");
+        fs::write(&filepath, "old").unwrap();
+        #[cfg(unix)]
+        fs::set_permissions(&filepath, Permissions::from_mode(0o751)).unwrap();
+        let updates = vec![update("a.toml", "[package]\nversion = \"1.1.0\"", "1.1.0")];
+
+        let mut transaction = Transaction::prepare(dir.path(), &updates).unwrap();
+        transaction.apply().unwrap();
+        assert_eq!(
+            fs::read_to_string(&filepath).unwrap(),
+            "[package]\nversion = \"1.1.0\""
+        );
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(&filepath).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o751);
+        }
+
+        transaction.rollback();
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "old");
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(&filepath).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o751);
+        }
+    }
+
+    #[test]
+    fn rollback_removes_added_files() {
+        let dir = TempDir::new().unwrap();
+        let filepath = dir.path().join("CHANGELOG.md");
+
+        let mut transaction = Transaction::prepare(dir.path(), &[]).unwrap();
+        transaction.add(filepath.clone(), "# Changelog\n").unwrap();
+        transaction.apply().unwrap();
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "# Changelog\n");
+
+        transaction.rollback();
+        assert!(!filepath.exists());
+    }
+}
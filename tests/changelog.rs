@@ -0,0 +1,205 @@
+mod common;
+
+use common::{json_config, package_json, Fixture, Step};
+
+fn history() -> Fixture {
+    Fixture::new(&[
+        Step::File("project.toml", &json_config(".", "v", "package.json")),
+        Step::File("package.json", &package_json("app", "1.0.0")),
+        Step::Commit("feat: first version"),
+        Step::Tag("v1.0.0"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix(api): a fix"),
+        Step::Tag("v1.0.1"),
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+        Step::File("README.md", "docs"),
+        Step::Commit("docs: readme"),
+        Step::Tag("v1.1.0"),
+        Step::Tag("unrelated"),
+        Step::File("src/later.js", "unreleased"),
+        Step::Commit("feat: not released yet"),
+    ])
+}
+
+/// The changelog without the commit ids, which differ between runs
+fn without_ids(changelog: &str) -> String {
+    changelog
+        .lines()
+        .map(|line| match line.rfind(" (") {
+            Some(start) if line.starts_with("- ") => &line[..start],
+            _ => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn rebuilds_the_whole_changelog() {
+    let fixture = history();
+    fixture.apply(&[Step::File("CHANGELOG.md", "outdated")]);
+    let output = fixture.run(&["changelog", "--rebuild"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let changelog = fixture.read("CHANGELOG.md");
+    assert_eq!(
+        without_ids(&changelog),
+        [
+            "# Changelog",
+            "",
+            "## 1.1.0",
+            "",
+            "### Features",
+            "",
+            "- a feature",
+            "",
+            "### Other changes",
+            "",
+            "- readme",
+            "",
+            "### Contributors",
+            "",
+            "- Test",
+            "",
+            "## 1.0.1",
+            "",
+            "### Bug fixes",
+            "",
+            "- **api:** a fix",
+            "",
+            "### Contributors",
+            "",
+            "- Test",
+            "",
+            "## 1.0.0",
+            "",
+            "### Features",
+            "",
+            "- first version",
+            "",
+            "### Contributors",
+            "",
+            "- Test",
+        ]
+        .join("\n")
+    );
+
+    // printing gives the same and running it again doesn't change anything
+    let output = fixture.run(&["changelog"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), changelog);
+    fixture.run(&["changelog", "--rebuild"]);
+    assert_eq!(fixture.read("CHANGELOG.md"), changelog);
+}
+
+#[test]
+fn prereleases_are_part_of_the_release() {
+    let fixture = Fixture::new(&[
+        Step::File("project.toml", &json_config(".", "v", "package.json")),
+        Step::File("package.json", &package_json("app", "1.0.0")),
+        Step::Commit("feat: first version"),
+        Step::Tag("v1.0.0"),
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+        Step::Tag("v1.1.0-beta.1"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix: a fix"),
+        Step::Tag("v1.1.0"),
+    ]);
+    let output = fixture.run(&["changelog"]);
+    assert!(output.status.success(), "{:?}", output);
+    let changelog = without_ids(&String::from_utf8_lossy(&output.stdout));
+    assert!(!changelog.contains("beta"), "{}", changelog);
+    assert!(
+        changelog.contains(
+            "## 1.1.0\n\n### Features\n\n- a feature\n\n### Bug fixes\n\n- a fix\n\n### Contributors\n\n- Test\n\n## 1.0.0\n"
+        ),
+        "{}",
+        changelog
+    );
+}
+
+#[test]
+fn calendar_versions_get_sections() {
+    let config = format!(
+        "{}\n[scheme]\ntype = \"calver\"\nformat = \"YYYY.0M\"\n",
+        json_config(".", "v", "package.json")
+    );
+    let fixture = Fixture::new(&[
+        Step::File("project.toml", &config),
+        Step::File("package.json", &package_json("app", "2026.09")),
+        Step::Commit("feat: first version"),
+        Step::Tag("v2026.09"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix: a fix"),
+        Step::Tag("v2026.10"),
+    ]);
+    let output = fixture.run(&["changelog"]);
+    assert!(output.status.success(), "{:?}", output);
+    let changelog = without_ids(&String::from_utf8_lossy(&output.stdout));
+    assert!(
+        changelog.starts_with("# Changelog\n\n## 2026.10\n\n### Bug fixes\n\n- a fix\n"),
+        "{}",
+        changelog
+    );
+    assert!(changelog.contains("## 2026.09\n"), "{}", changelog);
+
+    let output = fixture.run(&["notes", "v2026.10"]);
+    assert!(output.status.success(), "{:?}", output);
+    let notes = String::from_utf8_lossy(&output.stdout);
+    assert!(notes.starts_with("## 2026.10\n"), "{}", notes);
+    assert!(!notes.contains("first version"), "{}", notes);
+}
+
+#[test]
+fn releases_update_the_configured_changelog() {
+    let config = format!(
+        "changelog = \"CHANGELOG.md\"\n{}",
+        json_config(".", "v", "package.json")
+    );
+    let fixture = Fixture::new(&[
+        Step::File("project.toml", &config),
+        Step::File("package.json", &package_json("app", "1.0.0")),
+        Step::Commit("feat: first version"),
+        Step::Tag("v1.0.0"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix: a fix"),
+    ]);
+
+    // the dry run shows the new file without writing it
+    let output = fixture.run(&["--dry"]);
+    assert!(output.status.success(), "{:?}", output);
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("+++ b/CHANGELOG.md"), "{}", report);
+    assert!(!fixture.path().join("CHANGELOG.md").exists());
+
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!fixture.is_dirty());
+    let changelog = fixture.read("CHANGELOG.md");
+    assert_eq!(
+        without_ids(&changelog),
+        "# Changelog\n\n## 1.0.1\n\n### Bug fixes\n\n- a fix\n\n### Contributors\n\n- Test"
+    );
+    let committed = fixture.git(&["show", "--name-only", "--format=", "HEAD"]);
+    assert!(committed.contains("CHANGELOG.md"), "{}", committed);
+
+    // the next release goes on top, a rebuild only adds the release before the config had a changelog
+    fixture.apply(&[
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+    ]);
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    let changelog = fixture.read("CHANGELOG.md");
+    assert!(
+        changelog.starts_with("# Changelog\n\n## 1.1.0\n"),
+        "{}",
+        changelog
+    );
+    let rebuilt = fixture.run(&["changelog"]);
+    let first = fixture.run(&["notes", "v1.0.0"]);
+    assert_eq!(
+        String::from_utf8_lossy(&rebuilt.stdout),
+        format!("{}\n{}", changelog, String::from_utf8_lossy(&first.stdout))
+    );
+}
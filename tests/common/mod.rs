@@ -0,0 +1,159 @@
+// every test crate uses another part of the harness
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use semantic_release::{config::source, ReleaseOptions, Releaser};
+use tempfile::TempDir;
+
+/// One step of building a fixture repository
+pub enum Step<'a> {
+    /// Write a file, creating missing directories
+    File(&'a str, &'a str),
+    /// Commit everything with this message
+    Commit(&'a str),
+    /// Tag HEAD
+    Tag(&'a str),
+}
+
+/// A temporary git repository built from [Step]s
+pub struct Fixture {
+    dir: TempDir,
+}
+
+impl Fixture {
+    pub fn new(steps: &[Step]) -> Fixture {
+        let fixture = Fixture {
+            dir: TempDir::new().unwrap(),
+        };
+        fixture.git(&["init", "--quiet", "--initial-branch=main"]);
+        fixture.git(&["config", "user.name", "Test"]);
+        fixture.git(&["config", "user.email", "test@example.com"]);
+        fixture.git(&["config", "commit.gpgsign", "false"]);
+        fixture.apply(steps);
+        fixture
+    }
+
+    pub fn apply(&self, steps: &[Step]) {
+        for step in steps {
+            match step {
+                Step::File(path, content) => {
+                    let path = self.path().join(path);
+                    fs::create_dir_all(path.parent().unwrap()).unwrap();
+                    fs::write(path, content).unwrap();
+                }
+                Step::Commit(message) => {
+                    self.git(&["add", "--all"]);
+                    self.git(&["commit", "--quiet", "--allow-empty", "-m", message]);
+                }
+                Step::Tag(name) => {
+                    self.git(&["tag", name]);
+                }
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Runs git in the repository and returns its trimmed stdout
+    pub fn git(&self, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(self.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {:?}",
+            args,
+            output
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.path().join(path)).unwrap()
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.git(&["tag", "--list"])
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Summaries of all commits, newest first
+    pub fn subjects(&self) -> Vec<String> {
+        self.git(&["log", "--format=%s"])
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Whether the working tree has uncommitted changes
+    pub fn is_dirty(&self) -> bool {
+        !self.git(&["status", "--porcelain"]).is_empty()
+    }
+
+    /// The release engine for the config file at `config`, relative to the repository
+    pub fn releaser(&self, config: &str) -> Releaser {
+        self.releaser_with(config, ReleaseOptions::default())
+    }
+
+    pub fn releaser_with(&self, config: &str, options: ReleaseOptions) -> Releaser {
+        let path = self.path().join(config);
+        let (config, source) =
+            source::load(Some(path.to_str().unwrap()), &Default::default()).unwrap();
+        Releaser::new(config, &source.directory().unwrap(), options).unwrap()
+    }
+
+    /// Runs the semantic-release binary in the repository
+    pub fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_semantic-release"))
+            .args(args)
+            .current_dir(self.path())
+            .output()
+            .unwrap()
+    }
+
+    /// Runs the binary like [Fixture::run] with `home` as the home directory and without the system config,
+    /// so the global git config of the machine running the tests doesn't count
+    pub fn run_isolated(&self, home: &Path, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_semantic-release"))
+            .args(args)
+            .current_dir(self.path())
+            .env("HOME", home)
+            .env("XDG_CONFIG_HOME", home)
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env_remove("GIT_CONFIG_GLOBAL")
+            .env_remove("GIT_AUTHOR_NAME")
+            .env_remove("GIT_AUTHOR_EMAIL")
+            .env_remove("GIT_COMMITTER_NAME")
+            .env_remove("GIT_COMMITTER_EMAIL")
+            .env_remove("EMAIL")
+            .output()
+            .unwrap()
+    }
+}
+
+/// A config with one JSON file, `subpath` and `tagprefix` are written as given
+pub fn json_config(subpath: &str, tagprefix: &str, file: &str) -> String {
+    format!(
+        "subpath = {:?}\ntagprefix = {:?}\n\n[[files]]\npath = {:?}\nkey = \"version\"\ntype = \"json\"\n",
+        subpath, tagprefix, file
+    )
+}
+
+/// A package.json with the given version
+pub fn package_json(name: &str, version: &str) -> String {
+    format!(
+        "{{\n  \"name\": {:?},\n  \"version\": {:?}\n}}",
+        name, version
+    )
+}
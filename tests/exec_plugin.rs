@@ -0,0 +1,93 @@
+mod common;
+
+use std::{env, path::PathBuf, process::Command};
+
+use common::{Fixture, Step};
+
+/// The reference plugin, cargo test builds the examples next to the test binaries
+fn plugin() -> PathBuf {
+    let mut directory = env::current_exe().unwrap();
+    directory.pop();
+    if directory.ends_with("deps") {
+        directory.pop();
+    }
+    let plugin = directory
+        .join("examples")
+        .join(format!("properties_plugin{}", env::consts::EXE_SUFFIX));
+    if !plugin.exists() {
+        // only this test was selected, so the examples weren't built
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--example", "properties_plugin"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    plugin
+}
+
+/// `extra` is appended to the files entry
+fn repo(key: &str, extra: &str) -> Fixture {
+    let config = format!(
+        "subpath = \".\"\ntagprefix = \"v\"\n\n[[files]]\npath = \"build.properties\"\nkey = \"{}\"\ntype = \"exec\"\ncommand = {:?}\n{}",
+        key,
+        plugin().display().to_string(),
+        extra
+    );
+    Fixture::new(&[
+        Step::File(
+            "build.properties",
+            "# build descriptor\n# version=0.0.1\n! version=0.0.2\nname=service\nversion=1.2.3\n",
+        ),
+        Step::File("project.toml", &config),
+        Step::Commit("chore: initial"),
+        Step::Tag("v1.2.3"),
+        Step::File("feature.txt", "new"),
+        Step::Commit("feat: a feature"),
+    ])
+}
+
+#[test]
+fn releases_with_the_plugin() {
+    let fixture = repo("version", "");
+    let output = fixture.run(&["check"]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        fixture.read("build.properties"),
+        "# build descriptor\n# version=0.0.1\n! version=0.0.2\nname=service\nversion=1.3.0\n"
+    );
+    assert_eq!(fixture.tags(), vec!["v1.2.3", "v1.3.0"]);
+}
+
+#[test]
+fn plugin_errors_are_reported() {
+    let fixture = repo("missing", "");
+    let output = fixture.run(&[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the key missing doesn't exist"),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        fixture.read("build.properties"),
+        "# build descriptor\n# version=0.0.1\n! version=0.0.2\nname=service\nversion=1.2.3\n"
+    );
+}
+
+#[test]
+fn plugins_set_the_values() {
+    let fixture = repo(
+        "version",
+        "values = [{ key = \"name\", value = \"service-{version}\" }]\n",
+    );
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        fixture.read("build.properties"),
+        "# build descriptor\n# version=0.0.1\n! version=0.0.2\nname=service-1.3.0\nversion=1.3.0\n"
+    );
+}
@@ -0,0 +1,316 @@
+mod common;
+
+use std::sync::Arc;
+
+use tempfile::TempDir;
+
+use common::{json_config, package_json, Fixture, Step};
+use semantic_release::{
+    report::DiffFormat,
+    scheme::{Date, FixedClock},
+    ReleaseOptions,
+};
+
+/// The version in the package.json at `path`
+fn version(fixture: &Fixture, path: &str) -> String {
+    let json: serde_json::Value = serde_json::from_str(&fixture.read(path)).unwrap();
+    json["version"].as_str().unwrap().to_string()
+}
+
+fn single_package() -> Fixture {
+    Fixture::new(&[
+        Step::File("project.toml", &json_config(".", "v", "package.json")),
+        Step::File("package.json", &package_json("app", "1.0.0")),
+        Step::Commit("chore: initial"),
+        Step::Tag("v1.0.0"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix: a fix"),
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+    ])
+}
+
+#[test]
+fn releases_a_single_package() {
+    let fixture = single_package();
+    let releaser = fixture.releaser("project.toml");
+    let plan = releaser.plan().unwrap().unwrap();
+    releaser.apply(&plan).unwrap();
+
+    assert_eq!(version(&fixture, "package.json"), "1.1.0");
+    assert_eq!(fixture.tags(), vec!["v1.0.0", "v1.1.0"]);
+    assert_eq!(fixture.subjects()[0], "[Semantic release]: Release 1.1.0");
+    assert!(!fixture.is_dirty());
+
+    // the release commit itself doesn't lead to another release
+    assert!(fixture.releaser("project.toml").plan().unwrap().is_none());
+}
+
+#[test]
+fn breaking_changes_bump_the_major_version() {
+    let fixture = single_package();
+    fixture.apply(&[
+        Step::File("src/api.js", "changed"),
+        Step::Commit("feat: a new api\n\nBREAKING CHANGE: the old one is gone"),
+    ]);
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+
+    assert_eq!(version(&fixture, "package.json"), "2.0.0");
+    assert_eq!(fixture.tags(), vec!["v1.0.0", "v2.0.0"]);
+}
+
+const CHART: &str = "apiVersion: v2
+name: app
+description: A Helm chart for Kubernetes
+type: application
+# the version of the chart
+version: 0.3.0
+# the version of the app, quoted like helm create does
+appVersion: \"1.0.0\"
+dependencies:
+  - name: redis
+    version: 17.3.0
+    repository: https://charts.bitnami.com/bitnami
+";
+
+const COMPOSE: &str = "services:
+  app:
+    image: registry.example.com/app:1.0.0
+    ports:
+      - \"8080:8080\"
+  redis:
+    image: redis:7.2
+";
+
+#[test]
+fn sets_the_values_of_other_keys() {
+    let app = json_config("app", "app-v", "package.json")
+        + "\n[[files]]\npath = \"compose.yaml\"\nkey = \"image: registry.example.com/app:{version}\"\ntype = \"line\"\n";
+    let chart = "subpath = \"chart\"\ntagprefix = \"chart-v\"\n\n[[files]]\npath = \"Chart.yaml\"\nkey = \"version: {version}\"\ntype = \"line\"\nvalues = [{ key = \"appVersion: \\\"{value}\\\"\", value = \"{tag:app-v}\" }]\n";
+    let fixture = Fixture::new(&[
+        Step::File("app.release.toml", &app),
+        Step::File("chart.release.toml", chart),
+        Step::File("app/package.json", &package_json("app", "1.0.0")),
+        Step::File("app/compose.yaml", COMPOSE),
+        Step::File("chart/Chart.yaml", CHART),
+        Step::Commit("chore: initial"),
+        Step::Tag("app-v1.0.0"),
+        Step::Tag("chart-v0.3.0"),
+        Step::File("app/src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+        Step::File("chart/values.yaml", "replicaCount: 2\n"),
+        Step::Commit("fix: more replicas"),
+    ]);
+    for config in ["app.release.toml", "chart.release.toml"] {
+        let output = fixture.run(&["check", config]);
+        assert!(output.status.success(), "{:?}", output);
+        let releaser = fixture.releaser(config);
+        let plan = releaser.plan().unwrap().unwrap();
+        releaser.apply(&plan).unwrap();
+    }
+
+    assert_eq!(
+        fixture.read("app/compose.yaml"),
+        COMPOSE.replace("app:1.0.0", "app:1.1.0")
+    );
+    assert_eq!(
+        fixture.read("chart/Chart.yaml"),
+        CHART
+            .replace("version: 0.3.0", "version: 0.3.1")
+            .replace("\"1.0.0\"", "\"1.1.0\"")
+    );
+    assert_eq!(
+        fixture.tags(),
+        vec!["app-v1.0.0", "app-v1.1.0", "chart-v0.3.0", "chart-v0.3.1"]
+    );
+}
+
+#[test]
+fn releases_calendar_versions() {
+    let config = json_config(".", "v", "package.json")
+        + "\n[scheme]\ntype = \"calver\"\nformat = \"YYYY.0M.MICRO\"\n";
+    let fixture = Fixture::new(&[
+        Step::File("project.toml", &config),
+        Step::File("package.json", &package_json("service", "2026.09.3")),
+        Step::Commit("chore: initial"),
+        Step::Tag("v2026.09.3"),
+        Step::File("src/fix.js", "fixed"),
+        Step::Commit("fix: a fix"),
+    ]);
+    let release = |day| {
+        let releaser = fixture.releaser_with(
+            "project.toml",
+            ReleaseOptions {
+                clock: Arc::new(FixedClock(Date::new(2026, 10, day))),
+                ..ReleaseOptions::default()
+            },
+        );
+        let plan = releaser.plan().unwrap().unwrap();
+        releaser.apply(&plan).unwrap();
+    };
+
+    release(18);
+    assert_eq!(version(&fixture, "package.json"), "2026.10.0");
+
+    // chores alone still don't release anything
+    fixture.apply(&[
+        Step::File("README.md", "docs"),
+        Step::Commit("docs: readme"),
+    ]);
+    assert!(fixture.releaser("project.toml").plan().unwrap().is_none());
+
+    fixture.apply(&[
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+    ]);
+    release(20);
+    assert_eq!(version(&fixture, "package.json"), "2026.10.1");
+    assert_eq!(
+        fixture.tags(),
+        vec!["v2026.09.3", "v2026.10.0", "v2026.10.1"]
+    );
+}
+
+#[test]
+fn releases_one_package_of_a_monorepo() {
+    let fixture = Fixture::new(&[
+        Step::File("a.toml", &json_config("packages/a", "a-v", "package.json")),
+        Step::File("b.toml", &json_config("packages/b", "b-v", "package.json")),
+        Step::File("packages/a/package.json", &package_json("a", "1.0.0")),
+        Step::File("packages/b/package.json", &package_json("b", "2.3.0")),
+        Step::Commit("chore: initial"),
+        Step::Tag("a-v1.0.0"),
+        Step::Tag("b-v2.3.0"),
+        Step::File("packages/a/index.js", "new"),
+        Step::Commit("feat(a): a feature"),
+    ]);
+
+    // only commits inside the subpath count
+    assert!(fixture.releaser("b.toml").plan().unwrap().is_none());
+
+    let releaser = fixture.releaser("a.toml");
+    let plan = releaser.plan().unwrap().unwrap();
+    releaser.apply(&plan).unwrap();
+
+    assert_eq!(version(&fixture, "packages/a/package.json"), "1.1.0");
+    assert_eq!(version(&fixture, "packages/b/package.json"), "2.3.0");
+    assert_eq!(fixture.tags(), vec!["a-v1.0.0", "a-v1.1.0", "b-v2.3.0"]);
+    assert_eq!(
+        fixture.git(&["show", "--name-only", "--format=", "HEAD"]),
+        "packages/a/package.json"
+    );
+}
+
+#[test]
+fn dry_run_changes_nothing() {
+    let fixture = single_package();
+    let head = fixture.git(&["rev-parse", "HEAD"]);
+
+    let releaser = fixture.releaser("project.toml");
+    let plan = releaser.plan().unwrap().unwrap();
+    let report = releaser.dry_run(&plan).unwrap();
+    let rendered = report.render(DiffFormat::Unified).unwrap();
+    assert!(
+        rendered.contains("+  \"version\": \"1.1.0\""),
+        "{}",
+        rendered
+    );
+    assert!(rendered.contains("Tag: v1.1.0"), "{}", rendered);
+
+    let output = fixture.run(&["--dry"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Tag: v1.1.0"));
+
+    assert_eq!(version(&fixture, "package.json"), "1.0.0");
+    assert_eq!(fixture.tags(), vec!["v1.0.0"]);
+    assert_eq!(fixture.git(&["rev-parse", "HEAD"]), head);
+    assert!(!fixture.is_dirty());
+}
+
+#[test]
+fn missing_tag_releases_nothing() {
+    let fixture = Fixture::new(&[
+        Step::File("project.toml", &json_config(".", "v", "package.json")),
+        Step::File("package.json", &package_json("app", "1.0.0")),
+        Step::Commit("chore: initial"),
+        Step::File("src/feature.js", "new"),
+        Step::Commit("feat: a feature"),
+    ]);
+
+    assert!(fixture.releaser("project.toml").plan().unwrap().is_none());
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Could not find the tag v1.0.0"));
+
+    assert_eq!(version(&fixture, "package.json"), "1.0.0");
+    assert!(fixture.tags().is_empty());
+    assert_eq!(fixture.subjects().len(), 2);
+}
+
+#[test]
+fn unmatched_branches_release_nothing_by_default() {
+    let config = format!(
+        "branches = [\"main\"]\n{}",
+        json_config(".", "v", "package.json")
+    );
+    let fixture = single_package();
+    fixture.apply(&[
+        Step::File("project.toml", &config),
+        Step::Commit("chore: release from main only"),
+    ]);
+    fixture.git(&["checkout", "--quiet", "-b", "feature"]);
+
+    let output = fixture.run(&["--dry", "--allow-any-branch"]);
+    assert!(!output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--allow-unmatched-stable"));
+
+    let output = fixture.run(&["--dry", "--allow-any-branch", "--allow-unmatched-stable"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Tag: v1.1.0"));
+    assert_eq!(fixture.tags(), vec!["v1.0.0"]);
+}
+
+#[test]
+fn missing_identity_stops_the_release() {
+    let fixture = single_package();
+    fixture.git(&["config", "--unset", "user.name"]);
+    fixture.git(&["config", "--unset", "user.email"]);
+    let home = TempDir::new().unwrap();
+
+    let output = fixture.run_isolated(home.path(), &[]);
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no git identity"), "{}", stderr);
+    assert!(stderr.contains("--allow-missing-identity"), "{}", stderr);
+    assert_eq!(version(&fixture, "package.json"), "1.0.0");
+    assert_eq!(fixture.tags(), vec!["v1.0.0"]);
+
+    // the checks let it through, so it's git that can't create the commit
+    let output = fixture.run_isolated(home.path(), &["--allow-missing-identity"]);
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("pre-flight check(s) failed"), "{}", stderr);
+    assert!(stderr.contains("were restored"), "{}", stderr);
+    assert_eq!(version(&fixture, "package.json"), "1.0.0");
+    assert!(!fixture.is_dirty());
+}
+
+#[test]
+fn check_applies_the_overrides() {
+    let fixture = single_package();
+    let output = fixture.run(&["check", "--tagprefix", "app-v"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Using the tagprefix from --tagprefix"),
+        "{}",
+        stderr
+    );
+
+    let output = fixture.run(&["check", "project.toml", "--subpath", "missing"]);
+    assert!(!output.status.success(), "{:?}", output);
+
+    let output = fixture.run(&["lint", "--tagprefix", "app-v", "--range", "v1.0.0.."]);
+    assert!(output.status.success(), "{:?}", output);
+}